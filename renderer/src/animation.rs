@@ -2,7 +2,7 @@ use shared::types::{ AnimationCallbackEvent, AnimationState };
 
 #[no_mangle]
 pub fn update_animations(
-    animation_states: &mut Vec<AnimationState>,
+    animation_states: &mut [AnimationState],
     dt: f32
 ) -> Vec<AnimationCallbackEvent> {
     let mut res = Vec::new();
@@ -1,28 +1,21 @@
-use std::collections::VecDeque;
+#[cfg(feature = "debug")]
+use std::collections::{ HashSet, VecDeque };
 
+#[cfg(feature = "debug")]
 use shared::{
-    config::CHUNK_SIZE,
-    types::{ ChunkPos, ChunkVec3, Enemies, EnemyHandle, EntityType },
-    vec3,
-    Vec3,
-    BLUE,
-    RED, // dont use macroquad types here, then avoid dependency and then we could make it compile quicker ?
+    types::{ ChunkPos, ChunkVec3, EnemyHandle, EntityType },
+    BLUE, // dont use macroquad types here, then avoid dependency and then we could make it compile quicker ?
 };
+use shared::{ types::{ Enemies, LayoutIssue, WorldLayout }, Vec3, ORANGE, RED, YELLOW };
 
 use crate::Screen;
 
 #[no_mangle]
-pub fn render_enemy_world_positions(
-    screen: &Screen,
-    world_layout: &[
-        [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-        CHUNK_SIZE as usize
-    ],
-    enemies: &Enemies
-) {
+#[cfg_attr(not(feature = "debug"), allow(unused_variables))]
+pub fn render_enemy_world_positions(screen: &Screen, world_layout: &WorldLayout, enemies: &Enemies) {
     #[cfg(feature = "debug")]
-    for (handle, &position) in enemies.positions.iter().enumerate() {
-        render_enemy(screen, world_layout, position, EnemyHandle(handle as u16), true);
+    for (handle, body) in enemies.bodies.iter().enumerate() {
+        render_enemy(screen, world_layout, body.pos, EnemyHandle(handle as u16), true);
     }
 
     // for x in 0..world_layout.len() {
@@ -38,36 +31,54 @@ pub fn render_enemy_world_positions(
     // }
 }
 
+/// marks every tile `WorldLayout::check_consistency` complained about,
+/// red for occupancy, orange for stale handles and yellow for blocks
+#[no_mangle]
+pub fn render_layout_issues(screen: &Screen, issues: &[LayoutIssue]) {
+    for issue in issues {
+        let (tile, color) = match issue {
+            LayoutIssue::StaleHandle(tile, _) => (*tile, ORANGE),
+            LayoutIssue::MissingOccupancy(tile, _) |
+            LayoutIssue::ExtraOccupancy(tile, _) |
+            LayoutIssue::DuplicateOccupancy(tile, _) => (*tile, RED),
+            LayoutIssue::MissingBlock(tile) | LayoutIssue::ExtraBlock(tile) | LayoutIssue::SolidMismatch(tile) =>
+                (*tile, YELLOW),
+            LayoutIssue::ArrayLengthMismatch(_) => {
+                continue;
+            }
+        };
+        screen.drawer.draw_cube_wires(tile.as_vec3(), Vec3::splat(1.05), color);
+    }
+}
+
+#[cfg(feature = "debug")]
 fn render_enemy(
     screen: &Screen,
-    world_layout: &[
-        [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-        CHUNK_SIZE as usize
-    ],
+    world_layout: &WorldLayout,
     position: ChunkVec3,
     handle: EnemyHandle,
     is_flying: bool
 ) {
-    let mut visited = [[[false; CHUNK_SIZE as usize]; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+    let mut visited = HashSet::new();
     let mut queue = VecDeque::new();
     let mut occupied_tiles = Vec::new();
 
     let start_pos = position.to_chunk();
-    if start_pos.x >= CHUNK_SIZE || start_pos.y >= CHUNK_SIZE || start_pos.z >= CHUNK_SIZE {
+    if !world_layout.in_bounds(start_pos) {
         return;
     }
     queue.push_back(start_pos);
-    visited[start_pos.x as usize][start_pos.y as usize][start_pos.z as usize] = true;
+    visited.insert(start_pos);
     occupied_tiles.push(start_pos);
 
     while let Some(current_pos) = queue.pop_front() {
         for neighbor in get_neighbors(current_pos) {
             if
-                is_valid_position(neighbor) &&
-                !visited[neighbor.x as usize][neighbor.y as usize][neighbor.z as usize] &&
+                world_layout.in_bounds(neighbor) &&
+                !visited.contains(&neighbor) &&
                 is_enemy_tile(world_layout, neighbor, handle)
             {
-                visited[neighbor.x as usize][neighbor.y as usize][neighbor.z as usize] = true;
+                visited.insert(neighbor);
                 queue.push_back(neighbor);
                 occupied_tiles.push(neighbor);
             }
@@ -84,33 +95,24 @@ fn render_enemy(
     }
 }
 
-fn is_enemy_tile(
-    world_layout: &[
-        [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-        CHUNK_SIZE as usize
-    ],
-    pos: ChunkPos,
-    handle: EnemyHandle,
-
-) -> bool {
-    return  world_layout[pos.x as usize][pos.y as usize][pos.z as usize].contains(&EntityType::Enemy(handle));
+#[cfg(feature = "debug")]
+fn is_enemy_tile(world_layout: &WorldLayout, pos: ChunkPos, handle: EnemyHandle) -> bool {
+    world_layout.entities_at(pos).contains(&EntityType::Enemy(handle))
 }
 
+#[cfg(feature = "debug")]
 fn get_neighbors(pos: ChunkPos) -> [ChunkPos; 6] {
     [
-        ChunkPos::new(pos.x.wrapping_add(1), pos.y, pos.z),
-        ChunkPos::new(pos.x.wrapping_sub(1), pos.y, pos.z),
-        ChunkPos::new(pos.x, pos.y.wrapping_add(1), pos.z),
-        ChunkPos::new(pos.x, pos.y.wrapping_sub(1), pos.z),
-        ChunkPos::new(pos.x, pos.y, pos.z.wrapping_add(1)),
-        ChunkPos::new(pos.x, pos.y, pos.z.wrapping_sub(1)),
+        ChunkPos::new(pos.x + 1, pos.y, pos.z),
+        ChunkPos::new(pos.x - 1, pos.y, pos.z),
+        ChunkPos::new(pos.x, pos.y + 1, pos.z),
+        ChunkPos::new(pos.x, pos.y - 1, pos.z),
+        ChunkPos::new(pos.x, pos.y, pos.z + 1),
+        ChunkPos::new(pos.x, pos.y, pos.z - 1),
     ]
 }
 
-fn is_valid_position(pos: ChunkPos) -> bool {
-    pos.x < (CHUNK_SIZE as u8) && pos.y < (CHUNK_SIZE as u8) && pos.z < (CHUNK_SIZE as u8)
-}
-
+#[cfg(feature = "debug")]
 fn calculate_bounding_box(tiles: &[ChunkPos]) -> (Vec3, Vec3) {
     let mut min_pos = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
     let mut max_pos = Vec3::new(f32::MIN, f32::MIN, f32::MIN);
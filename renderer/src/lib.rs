@@ -3,11 +3,12 @@ use std::f32::consts::PI;
 
 use shared::{
     config::{ SCREEN_HEIGHT, SCREEN_WIDTH, TILE_SIZE }, types::{
-        AnimationState, ChunkVec3, CustomCamera3D, Enemies, EnemyType, PossibleEnemySizes, VoxelMesh, WeaponType
-    }, vec2, vec3, Camera, Camera3D, Color, DrawRectangleParams, Vec2, Vec3, GRAY, GREEN, RED, WHITE, YELLOW // dont use macroquad types here, then avoid dependency and then we could make it compile quicker ?
+        AnimationState, BlockRegistry, ChunkVec3, Enemies, EnemyType, InteractableBlockType, InteractableBlocks, PossibleEnemySizes, SolidBlocks, VoxelMesh, WeaponType
+    }, vec2, vec3, Color, DrawRectangleParams, Vec2, Vec3, GOLD, GRAY, PURPLE, RED, WHITE, YELLOW // dont use macroquad types here, then avoid dependency and then we could make it compile quicker ?
 };
 pub mod animation;
 pub mod debug;
+pub mod texture;
 pub trait Drawer {
     fn draw_cube_wires(&self, position: Vec3, size: Vec3, color: Color);
     fn draw_rectangle(&self, position: Vec2, width: f32, height: f32, color: Color);
@@ -36,21 +37,43 @@ pub struct Screen {
 }
 
 #[no_mangle]
-pub fn render_solid_blocks(screen: &Screen, positions: &Vec<ChunkVec3>) {
-    for pos in positions {
+pub fn render_solid_blocks(screen: &Screen, solid_blocks: &SolidBlocks, registry: &BlockRegistry) {
+    for (pos, b_type) in solid_blocks.positions.iter().zip(solid_blocks.b_types.iter()) {
         let pos = pos.0;
-        screen.drawer.draw_cube_wires(vec3(pos.x, pos.y, pos.z), Vec3::splat(TILE_SIZE), GREEN);
+        let properties = registry.get(*b_type);
+        if let Some(facing) = properties.ramp {
+            // two half steps rising towards the facing, close enough to the slope the movement uses
+            let dir = facing.dir();
+            let half = TILE_SIZE * 0.5;
+            let upper_size = Vec3::splat(TILE_SIZE) - dir.abs() * half - vec3(0.0, half, 0.0);
+            screen.drawer.draw_cube_wires(pos - vec3(0.0, half * 0.5, 0.0), vec3(TILE_SIZE, half, TILE_SIZE), properties.color);
+            screen.drawer.draw_cube_wires(pos + dir * half * 0.5 + vec3(0.0, half * 0.5, 0.0), upper_size, properties.color);
+            continue;
+        }
+        screen.drawer.draw_cube_wires(vec3(pos.x, pos.y, pos.z), Vec3::splat(TILE_SIZE), properties.color);
     }
 }
 #[no_mangle]
-pub fn render_regular_enemies(screen: &Screen, enemies: &Enemies) {
-    for (i, enemy) in enemies.positions.iter().enumerate() {
+pub fn render_interactable_blocks(screen: &Screen, interactable_blocks: &InteractableBlocks) {
+    for (pos, b_type) in interactable_blocks.positions.iter().zip(interactable_blocks.b_types.iter()) {
+        let color = match b_type {
+            InteractableBlockType::Trampoline(_) => YELLOW,
+            InteractableBlockType::Portal { .. } => PURPLE,
+            InteractableBlockType::Stairs => GOLD,
+        };
+        screen.drawer.draw_cube_wires(pos.0, Vec3::splat(TILE_SIZE), color);
+    }
+}
+#[no_mangle]
+pub fn render_regular_enemies(screen: &Screen, enemies: &Enemies, alpha: f32) {
+    for (i, body) in enemies.bodies.iter().enumerate() {
+        let pos = body.interpolated(alpha);
         #[cfg(not(feature = "debug"))]
         render_default_enemy(
             screen,
             enemies.e_type[i],
-            *enemy,
-            enemies.velocities[i],
+            pos,
+            body.vel,
             enemies.size[i],
             enemies.animation_state[i].current_step,
             enemies.animation_state[i].max_step
@@ -59,8 +82,8 @@ pub fn render_regular_enemies(screen: &Screen, enemies: &Enemies) {
         render_default_enemy_with_hitbox(
             screen,
             enemies.e_type[i],
-            *enemy,
-            enemies.velocities[i],
+            pos,
+            body.vel,
             enemies.size[i],
             enemies.animation_state[i].current_step,
             enemies.animation_state[i].max_step
@@ -70,10 +93,10 @@ pub fn render_regular_enemies(screen: &Screen, enemies: &Enemies) {
 #[no_mangle]
 pub fn render_flying_enemies(
     screen: &Screen,
-    positions: &Vec<ChunkVec3>,
-    velocities: &Vec<Vec3>,
-    animations: &Vec<AnimationState>,
-    sizes: &Vec<PossibleEnemySizes>
+    positions: &[ChunkVec3],
+    velocities: &[Vec3],
+    animations: &[AnimationState],
+    sizes: &[PossibleEnemySizes]
 ) {
     for (i, enemy) in positions.iter().enumerate() {
         #[cfg(not(feature = "debug"))]
@@ -254,10 +277,10 @@ pub fn render_default_enemy_with_hitbox(
 pub fn render_flying_enemy(
     screen: &Screen,
     pos: ChunkVec3,
-    vel: Vec3,
+    _vel: Vec3,
     size: PossibleEnemySizes,
     animation_step: f32,
-    max_animation_step: f32
+    _max_animation_step: f32
 ) {
     let scale = Enemies::get_vec3_size(size);
     let pos = pos.0;
@@ -271,10 +294,10 @@ pub fn render_flying_enemy(
 pub fn render_flying_enemy_with_hitbox(
     screen: &Screen,
     pos: ChunkVec3,
-    vel: Vec3,
+    _vel: Vec3,
     size: PossibleEnemySizes,
     animation_step: f32,
-    max_animation_step: f32
+    _max_animation_step: f32
 ) {
     let scale = Enemies::get_vec3_size(size);
     let pos = pos.0;
@@ -296,7 +319,7 @@ pub fn render_player_pov(
 ) {
     const SCREEN_X_OFFSET: f32 = (SCREEN_WIDTH as f32) / 2.0;
     const SCREEN_Y_OFFSET: f32 = (SCREEN_HEIGHT as f32) / 2.0;
-    let bobbing = (animation_state.current_step * PI).sin() * 0.25;
+    let _bobbing = (animation_state.current_step * PI).sin() * 0.25;
     // CROSSHAIR
     screen.drawer.draw_circle_lines(vec2(SCREEN_X_OFFSET, SCREEN_Y_OFFSET), 5.0, WHITE);
 
@@ -1,15 +1,47 @@
 use macroquad::{math::{vec3, Vec3}, window::Conf};
 pub const PHYSICS_FRAMES_PER_SECOND: f32 = 60.0;
 pub const PHYSICS_FRAME_TIME: f32 = 1.0 / 60.0;
+pub const MAX_FRAME_TIME: f32 = 0.25; // seconds simulated per rendered frame at most, longer hitches slow the game down instead
 pub const MOVE_SPEED: f32 = 5.0;
-pub const ENEMY_DEFAULT_MOVE_SPEED: f32 = 1.0;
-pub const LOOK_SPEED: f32 = 40.0;
+// body velocities are in tiles per second, the player ones are multiples of MOVE_SPEED
+pub const WALK_SPEED: f32 = MOVE_SPEED;
+pub const SPRINT_SPEED: f32 = 1.6 * MOVE_SPEED;
+pub const CROUCH_SPEED: f32 = 0.5 * MOVE_SPEED;
+pub const GROUND_ACCELERATION: f32 = 10.0; // per second, scaled by the friction of the ground block
+pub const GROUND_FRICTION: f32 = 8.0; // per second, scaled by the friction of the ground block
+pub const AIR_CONTROL: f32 = 0.3; // share of the ground acceleration left in the air
+pub const DASH_SPEED: f32 = 4.0 * MOVE_SPEED;
+pub const DASH_TIME: f32 = 0.15; // seconds
+pub const DASH_COOLDOWN: f32 = 1.0; // seconds
+pub const DASH_INVULNERABILITY: f32 = 0.3; // seconds without enemy contact damage after starting a dash
+pub const ENEMY_CONTACT_REACH: f32 = 0.5; // contact counts a bit before the hitboxes touch, enemies push against the player from there
+pub const ENEMY_CONTACT_SHOVE: f32 = 12.0; // impulse per second and unit of enemy mass while touching the player
+pub const SHOTGUN_KNOCKBACK: f32 = 15.0; // impulse of a single hit, a small enemy weighs 1
+pub const ENEMY_STEERING: f32 = 6.0; // per second, how quickly enemies turn towards the player and pushes wear off
+pub const ENEMY_SEPARATION: f32 = 0.5; // tiles of room enemies try to keep between each other
+pub const ENEMY_SEPARATION_STRENGTH: f32 = 4.0; // tiles per second away from an enemy standing on the same spot
+pub const ENEMY_AVOID_DISTANCE: f32 = 1.0; // tiles ahead enemies look for walls to walk around
+pub const ENEMY_DEFAULT_MOVE_SPEED: f32 = 0.8 * MOVE_SPEED; // top pursuit speed, a walking player still gets away
+pub const BOSS_EXPLOSION_RADIUS: f32 = 3.0; // tiles, a dying boss blows up the blocks and enemies around it
+pub const LOOK_SPEED: f32 = 0.67; // radians per mouse movement, see mouse_delta_position
+pub const MAX_PITCH: f32 = 1.5; // radians the player can look up or down
 pub const WORLD_UP: Vec3 = vec3(0.0, 1.0, 0.0);
 pub const INITIAL_PLAYER_POS: Vec3 = vec3(7.0, 1.0, 7.0);
 pub const CHUNK_SIZE: u8 = 16;
+pub const MAX_WORLD_CHUNKS: i32 = 4096; // levels bigger than this get rejected, a chunk takes about 4.5 KB
 pub const MAX_BLOCK_TYPES: u8 = u8::MAX;
-pub const GRAVITY: f32 = -9.81;
-pub const JUMP_STRENGTH: f32 = 3.0;
+pub const PLAYER_MAX_HEALTH: f32 = 100.0;
+pub const PLAYER_MASS: f32 = 2.0;
+pub const PLAYER_HALF_HITBOX: Vec3 = vec3(0.3, 0.9, 0.3);
+pub const PLAYER_CROUCH_HALF_HEIGHT: f32 = 0.55;
+pub const PLAYER_EYE_HEIGHT: f32 = 0.7; // above the center of the standing hitbox
+pub const GRAVITY: f32 = -9.81 * MOVE_SPEED;
+pub const STEP_HEIGHT: f32 = 1.0; // tiles, ledges up to this height get walked up without jumping
+pub const JUMP_STRENGTH: f32 = 3.0 * MOVE_SPEED;
+pub const COYOTE_TIME: f32 = 0.1; // seconds
+pub const JUMP_BUFFER_TIME: f32 = 0.12; // seconds
+pub const JUMP_CUT: f32 = 0.5; // upward velocity kept when the jump key is released early
+pub const TRAMPOLINE_DEFAULT_HEIGHT: f32 = 6.0; // tiles
 pub const TILE_SIZE: f32 = 1.0;
 pub const SCREEN_WIDTH: i32 = 1920;
 pub const SCREEN_HEIGHT: i32 = 1080;
@@ -1,19 +1,29 @@
-use std::ops::Add;
+use std::{collections::HashMap, f32::consts::PI, ops::Add};
 
-use macroquad::{camera::{Camera, Camera3D}, math::{vec3, Mat4, Vec3}, texture::RenderPass};
+use macroquad::{
+    camera::{Camera, Camera3D},
+    color::{BLANK, BROWN, DARKGRAY, GREEN, LIGHTGRAY, MAROON, ORANGE, SKYBLUE},
+    math::{vec3, Mat4, Vec2, Vec3},
+    texture::RenderPass,
+};
 
 use crate::{
-    config::{CHUNK_SIZE, INITIAL_PLAYER_POS},
+    config::{CHUNK_SIZE, DASH_COOLDOWN, INITIAL_PLAYER_POS, MAX_BLOCK_TYPES, PLAYER_CROUCH_HALF_HEIGHT, PLAYER_EYE_HEIGHT, PLAYER_HALF_HITBOX, PLAYER_MASS, PLAYER_MAX_HEALTH, MAX_PITCH},
     types::{
-        AnimationCallbackEvent, AnimationState, ChunkPos, ChunkVec3, CurrWeapon, CustomCamera3D, Enemies, EnemyHandle, EnemyType, EntityType, MaxWeapon, Player, PossibleEnemySizes, SolidBlocks, Weapon, WeaponType, WorldEvent
+        CHUNK_TILE_COUNT, AnimationCallbackEvent, BlockProperties, BlockRegistry, BlockTypeId, AnimationState, Body, Chunk, ChunkCoord, ChunkPos, ChunkVec3, CurrWeapon, CustomCamera3D, DashState, Enemies, EnemyHandle, EnemyType, EntityType, Facing, InteractableBlockType, InteractableBlocks, JumpState, LayoutIssue, LocalPos, MaxWeapon, MoveInput, Player, PossibleEnemySizes, Rng, RunRng, SolidBlocks, TickInput, Weapon, WeaponType, WorldLayout
     },
 };
 
+impl Default for Enemies {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Enemies {
     pub fn new() -> Self {
         Enemies {
-            positions: Vec::new(),
-            velocities: Vec::new(),
+            bodies: Vec::new(),
             animation_state: Vec::new(),
             size: Vec::new(),
             healths: Vec::new(),
@@ -32,23 +42,28 @@ impl Enemies {
             match curr_e_type {
                 EnemyType::Empty => {
                     *curr_e_type = e_type;
-                    self.positions[idx] = pos;
-                    self.velocities[idx] = vel;
-                    self.animation_state.push(AnimationState::default());
+                    self.bodies[idx] = Self::body_for(pos, vel, size);
+                    self.animation_state[idx] = AnimationState::default();
                     self.size[idx] = size;
                     self.healths[idx] = health;
                     return EnemyHandle(idx as u16);
                 }
-                _ => {continue;}
+                _ => {
+                    continue;
+                }
             }
         }
-        self.positions.push(pos);
-        self.velocities.push(vel);
+        self.bodies.push(Self::body_for(pos, vel, size));
         self.animation_state.push(AnimationState::default());
         self.size.push(size);
         self.healths.push(health);
         self.e_type.push(e_type);
-        return EnemyHandle((self.positions.len() - 1) as u16);
+        EnemyHandle((self.bodies.len() - 1) as u16)
+    }
+    fn body_for(pos: ChunkVec3, vel: Vec3, size: PossibleEnemySizes) -> Body {
+        let mut body = Body::new(pos, Self::get_hitbox_from_size(size) * 0.5, Self::get_mass_from_size(size));
+        body.vel = vel;
+        body
     }
     pub fn get_vec3_size(size: PossibleEnemySizes) -> Vec3 {
         match size {
@@ -66,40 +81,315 @@ impl Enemies {
             PossibleEnemySizes::BOSS => Vec3::splat(1.25) * 2.0,
         }
     }
+    /// per second while touching the player
+    pub fn get_contact_damage_from_size(size: PossibleEnemySizes) -> f32 {
+        match size {
+            PossibleEnemySizes::SMALL => 5.0,
+            PossibleEnemySizes::MEDIUM => 10.0,
+            PossibleEnemySizes::LARGE => 15.0,
+            PossibleEnemySizes::BOSS => 25.0,
+        }
+    }
+    /// impulses are divided by it, bigger enemies barely move when shot
+    pub fn get_mass_from_size(size: PossibleEnemySizes) -> f32 {
+        match size {
+            PossibleEnemySizes::SMALL => 1.0,
+            PossibleEnemySizes::MEDIUM => 2.0,
+            PossibleEnemySizes::LARGE => 4.0,
+            PossibleEnemySizes::BOSS => 10.0,
+        }
+    }
+    pub fn get_health_from_size(size: PossibleEnemySizes) -> u8 {
+        match size {
+            PossibleEnemySizes::SMALL => 1,
+            PossibleEnemySizes::BOSS => 10,
+            PossibleEnemySizes::LARGE => 5,
+            PossibleEnemySizes::MEDIUM => 3,
+        }
+    }
     pub fn remove_enemy(&mut self, h: EnemyHandle) {
         let index = h.0 as usize;
-        if index < self.positions.len() {
+        if index < self.bodies.len() {
             self.e_type[index] = EnemyType::Empty;
         }
     }
     pub fn get_occupied_tiles(pos: &ChunkVec3, half_hitbox: &Vec3) -> Vec<ChunkPos> {
-        let mut res =Vec::new();
+        let mut res = Vec::new();
         let start = ChunkVec3(pos.0 - *half_hitbox).to_chunk();
         let end = ChunkVec3(pos.0 + *half_hitbox).to_chunk();
         for x in start.x..=end.x {
             for y in start.y..=end.y {
                 for z in start.z..=end.z {
-                    if x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE {
-                        res.push(ChunkPos::new(x, y, z));
-                    }
+                    res.push(ChunkPos::new(x, y, z));
                 }
             }
         }
 
         res
-    } 
+    }
+}
+
+impl Default for SolidBlocks {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SolidBlocks {
     pub fn new() -> Self {
         SolidBlocks {
             positions: Vec::new(),
+            b_types: Vec::new(),
+            hit_points: Vec::new(),
         }
     }
-    pub fn new_block(&mut self, pos: ChunkVec3) {
+    pub fn new_block(&mut self, pos: ChunkVec3, b_type: BlockTypeId, hit_points: u8) {
         self.positions.push(pos);
+        self.b_types.push(b_type);
+        self.hit_points.push(hit_points);
+    }
+    pub fn index_of(&self, pos: ChunkPos) -> Option<usize> {
+        self.positions.iter().position(|p| p.to_chunk() == pos)
+    }
+    /// order is not preserved, blocks are not referenced by index anywhere else
+    pub fn remove_block(&mut self, index: usize) {
+        self.positions.swap_remove(index);
+        self.b_types.swap_remove(index);
+        self.hit_points.swap_remove(index);
+    }
+}
+
+impl BlockTypeId {
+    pub const AIR: BlockTypeId = BlockTypeId(0);
+    pub const STONE: BlockTypeId = BlockTypeId(1);
+    pub const CRATE: BlockTypeId = BlockTypeId(2);
+    pub const ICE: BlockTypeId = BlockTypeId(3);
+    pub const LAVA: BlockTypeId = BlockTypeId(4);
+    pub const GLASS: BlockTypeId = BlockTypeId(5);
+    pub const DOOR: BlockTypeId = BlockTypeId(6);
+    pub const RAMP_POS_X: BlockTypeId = BlockTypeId(7);
+    pub const RAMP_NEG_X: BlockTypeId = BlockTypeId(8);
+    pub const RAMP_POS_Z: BlockTypeId = BlockTypeId(9);
+    pub const RAMP_NEG_Z: BlockTypeId = BlockTypeId(10);
+
+    /// ramp rising towards `facing`
+    pub fn ramp(facing: Facing) -> BlockTypeId {
+        match facing {
+            Facing::PosX => BlockTypeId::RAMP_POS_X,
+            Facing::NegX => BlockTypeId::RAMP_NEG_X,
+            Facing::PosZ => BlockTypeId::RAMP_POS_Z,
+            Facing::NegZ => BlockTypeId::RAMP_NEG_Z,
+        }
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        let mut registry = BlockRegistry { blocks: Vec::new() };
+        let stone = BlockProperties {
+            name: "stone",
+            solid: true,
+            destructible: false,
+            hit_points: 0,
+            color: GREEN,
+            friction: 1.0,
+            damage_on_touch: 0.0,
+            ramp: None,
+        };
+        registry.register(BlockProperties { name: "air", solid: false, color: BLANK, ..stone });
+        registry.register(stone);
+        registry.register(BlockProperties {
+            name: "crate",
+            destructible: true,
+            hit_points: 3,
+            color: BROWN,
+            ..stone
+        });
+        registry.register(BlockProperties { name: "ice", color: SKYBLUE, friction: 0.1, ..stone });
+        registry.register(BlockProperties {
+            name: "lava",
+            color: ORANGE,
+            damage_on_touch: 20.0,
+            ..stone
+        });
+        let glass = registry.register(BlockProperties {
+            name: "glass",
+            destructible: true,
+            hit_points: 1,
+            color: LIGHTGRAY,
+            ..stone
+        });
+        debug_assert_eq!(glass, BlockTypeId::GLASS);
+        let door = registry.register(BlockProperties { name: "door", color: MAROON, ..stone });
+        debug_assert_eq!(door, BlockTypeId::DOOR);
+        for (name, facing) in [
+            ("ramp_+x", Facing::PosX),
+            ("ramp_-x", Facing::NegX),
+            ("ramp_+z", Facing::PosZ),
+            ("ramp_-z", Facing::NegZ),
+        ] {
+            let ramp = registry.register(BlockProperties {
+                name,
+                solid: false,
+                color: DARKGRAY,
+                ramp: Some(facing),
+                ..stone
+            });
+            debug_assert_eq!(ramp, BlockTypeId::ramp(facing));
+        }
+        registry
     }
 }
+
+impl BlockRegistry {
+    pub fn register(&mut self, properties: BlockProperties) -> BlockTypeId {
+        assert!(self.blocks.len() < MAX_BLOCK_TYPES as usize, "too many block types");
+        self.blocks.push(properties);
+        BlockTypeId((self.blocks.len() - 1) as u8)
+    }
+    /// unknown ids behave like air
+    pub fn get(&self, id: BlockTypeId) -> &BlockProperties {
+        self.blocks.get(id.0 as usize).unwrap_or(&self.blocks[BlockTypeId::AIR.0 as usize])
+    }
+    pub fn find(&self, name: &str) -> Option<BlockTypeId> {
+        self.blocks
+            .iter()
+            .position(|block| block.name == name)
+            .map(|index| BlockTypeId(index as u8))
+    }
+}
+impl Default for InteractableBlocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InteractableBlocks {
+    pub fn new() -> Self {
+        InteractableBlocks {
+            positions: Vec::new(),
+            b_types: Vec::new(),
+            exits: Vec::new(),
+        }
+    }
+    pub fn new_block(&mut self, pos: ChunkVec3, b_type: InteractableBlockType) -> u16 {
+        self.positions.push(pos);
+        self.b_types.push(b_type);
+        self.exits.push(None);
+        (self.positions.len() - 1) as u16
+    }
+    /// pairs up portals with the same link id, a portal without a partner stays closed
+    pub fn link_portals(&mut self) {
+        for i in 0..self.b_types.len() {
+            let InteractableBlockType::Portal { link, .. } = self.b_types[i] else {
+                continue;
+            };
+            if self.exits[i].is_some() {
+                continue;
+            }
+            let partner = (i + 1..self.b_types.len()).find(|&j| {
+                self.exits[j].is_none() &&
+                    matches!(self.b_types[j], InteractableBlockType::Portal { link: other, .. } if other == link)
+            });
+            if let Some(j) = partner {
+                self.exits[i] = Some(j as u16);
+                self.exits[j] = Some(i as u16);
+            }
+        }
+    }
+    /// first linked portal in the given tiles
+    pub fn portal_in(&self, entities: &[EntityType]) -> Option<u16> {
+        entities.iter().find_map(|entity| match entity {
+            EntityType::InteractableBlock(index) if self.exits[*index as usize].is_some() =>
+                Some(*index),
+            _ => None,
+        })
+    }
+    pub fn facing(&self, index: u16) -> Option<Facing> {
+        match self.b_types[index as usize] {
+            InteractableBlockType::Portal { facing, .. } => Some(facing),
+            _ => None,
+        }
+    }
+    /// launch height of the first trampoline in the given tiles
+    pub fn trampoline_in(&self, entities: &[EntityType]) -> Option<f32> {
+        entities.iter().find_map(|entity| match entity {
+            EntityType::InteractableBlock(index) =>
+                match self.b_types[*index as usize] {
+                    InteractableBlockType::Trampoline(height) => Some(height),
+                    _ => None,
+                }
+            _ => None,
+        })
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// uniform in [0, 1)
+    pub fn gen_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+    /// uniform in [min, max), returns min for empty ranges
+    pub fn gen_range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        min + (self.next_u64() % ((max - min) as u64)) as i32
+    }
+    pub fn gen_range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.gen_f32()
+    }
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.gen_f32() < probability
+    }
+}
+
+impl RunRng {
+    pub fn new(seed: u64) -> Self {
+        RunRng {
+            seed,
+            spawning: RunRng::stream(seed, "spawning"),
+            loot: RunRng::stream(seed, "loot"),
+            ai: RunRng::stream(seed, "ai"),
+        }
+    }
+    /// mixes the name into the seed (FNV-1a), streams stay apart even for small seeds
+    fn stream(seed: u64, name: &str) -> Rng {
+        let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3));
+        Rng::new(seed ^ hash)
+    }
+}
+
+impl Facing {
+    /// player yaw looking out of the portal
+    pub fn yaw(&self) -> f32 {
+        match self {
+            Facing::PosX => 0.0,
+            Facing::PosZ => PI * 0.5,
+            Facing::NegX => PI,
+            Facing::NegZ => PI * 1.5,
+        }
+    }
+    pub fn dir(&self) -> Vec3 {
+        match self {
+            Facing::PosX => vec3(1.0, 0.0, 0.0),
+            Facing::PosZ => vec3(0.0, 0.0, 1.0),
+            Facing::NegX => vec3(-1.0, 0.0, 0.0),
+            Facing::NegZ => vec3(0.0, 0.0, -1.0),
+        }
+    }
+}
+
 impl Default for AnimationState {
     fn default() -> Self {
         AnimationState {
@@ -130,19 +420,99 @@ impl Weapon {
 impl Default for Player {
     fn default() -> Self {
         Player {
-            pos: ChunkVec3(INITIAL_PLAYER_POS),
-            vel: Vec3::ZERO,
+            body: Body::new(ChunkVec3(INITIAL_PLAYER_POS), PLAYER_HALF_HITBOX, PLAYER_MASS),
             yaw: 0.77,
             pitch: 0.0,
             weapon_unlocked: MaxWeapon(1),
             curr_weapon: CurrWeapon(0),
             weapons: [Weapon::new_shotgun()],
             animation_state: AnimationState::default(),
+            health: PLAYER_MAX_HEALTH,
+            jump: JumpState::default(),
+            input: MoveInput::default(),
+            crouching: false,
+            dash: DashState::default(),
         }
     }
 }
 
+impl Body {
+    pub fn new(pos: ChunkVec3, half_extents: Vec3, mass: f32) -> Self {
+        Body { pos, prev_pos: pos, vel: Vec3::ZERO, half_extents, mass, grounded: false }
+    }
+    /// position between the start (0) and the end (1) of the last physics step
+    pub fn interpolated(&self, alpha: f32) -> ChunkVec3 {
+        ChunkVec3(self.prev_pos.0.lerp(self.pos.0, alpha))
+    }
+    pub fn min(&self) -> Vec3 {
+        self.pos.0 - self.half_extents
+    }
+    pub fn max(&self) -> Vec3 {
+        self.pos.0 + self.half_extents
+    }
+}
+
+impl TickInput {
+    /// keeps what is held down for the next physics step, presses and mouse movement only count once
+    pub fn end_tick(&mut self) {
+        *self = TickInput {
+            jump_held: self.jump_held,
+            sprint: self.sprint,
+            crouch: self.crouch,
+            movement: self.movement,
+            ..Default::default()
+        };
+    }
+}
+
+impl DashState {
+    pub fn is_dashing(&self) -> bool {
+        self.time_left > 0.0
+    }
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.0
+    }
+    /// 1 right after dashing, 0 once the next dash is ready
+    pub fn cooldown_fraction(&self) -> f32 {
+        (self.cooldown / DASH_COOLDOWN).clamp(0.0, 1.0)
+    }
+}
+
 impl Player {
+    /// the body position is the center of the hitbox, the camera and shots start from here
+    pub fn eye_pos(&self) -> ChunkVec3 {
+        self.eye_pos_at(1.0)
+    }
+    /// the eye position interpolated like `Body::interpolated`, for the camera
+    pub fn eye_pos_at(&self, alpha: f32) -> ChunkVec3 {
+        let crouch_drop = PLAYER_HALF_HITBOX.y - self.body.half_extents.y;
+        ChunkVec3(self.body.interpolated(alpha).0 + vec3(0.0, PLAYER_EYE_HEIGHT - crouch_drop, 0.0))
+    }
+    pub fn look_dir(&self) -> Vec3 {
+        self.look_dir_after(Vec2::ZERO)
+    }
+    /// where the player looks once `look` got applied, the camera shows mouse movement the next physics
+    /// step has not used yet
+    pub fn look_dir_after(&self, look: Vec2) -> Vec3 {
+        let yaw = self.yaw + look.x;
+        let pitch = (self.pitch + look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        vec3(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos()).normalize()
+    }
+    pub fn turn(&mut self, look: Vec2) {
+        self.yaw += look.x;
+        self.pitch = (self.pitch + look.y).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+    pub fn half_hitbox_for(crouching: bool) -> Vec3 {
+        if crouching {
+            vec3(PLAYER_HALF_HITBOX.x, PLAYER_CROUCH_HALF_HEIGHT, PLAYER_HALF_HITBOX.z)
+        } else {
+            PLAYER_HALF_HITBOX
+        }
+    }
+    /// where the hitbox center goes for a player standing in the given tile
+    pub fn standing_in(tile: Vec3) -> ChunkVec3 {
+        ChunkVec3(tile + vec3(0.0, PLAYER_HALF_HITBOX.y - 0.5, 0.0))
+    }
     pub fn get_current_weapon(&self) -> &Weapon {
         &self.weapons[self.curr_weapon.0]
     }
@@ -154,34 +524,341 @@ impl Player {
         }
     }
     pub fn swap_next_weapon(&mut self) {
-        self.curr_weapon = CurrWeapon((self.curr_weapon.0 + 1) % (self.weapon_unlocked.0 - 1));
+        if self.weapon_unlocked.0 < 2 {
+            return;
+        }
+        self.curr_weapon = CurrWeapon((self.curr_weapon.0 + 1) % self.weapon_unlocked.0);
     }
-
 }
 
 impl ChunkPos {
-    pub fn new(x: u8, y: u8, z: u8) -> Self {
-        ChunkPos {
-            x,
-            y,
-            z,
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        ChunkPos { x, y, z }
+    }
+    pub fn from_parts(chunk: ChunkCoord, local: LocalPos) -> Self {
+        const SIZE: i32 = CHUNK_SIZE as i32;
+        ChunkPos::new(
+            chunk.x * SIZE + local.x as i32,
+            chunk.y * SIZE + local.y as i32,
+            chunk.z * SIZE + local.z as i32,
+        )
+    }
+    pub fn chunk(&self) -> ChunkCoord {
+        const SIZE: i32 = CHUNK_SIZE as i32;
+        ChunkCoord::new(self.x.div_euclid(SIZE), self.y.div_euclid(SIZE), self.z.div_euclid(SIZE))
+    }
+    pub fn local(&self) -> LocalPos {
+        const SIZE: i32 = CHUNK_SIZE as i32;
+        LocalPos {
+            x: self.x.rem_euclid(SIZE) as u8,
+            y: self.y.rem_euclid(SIZE) as u8,
+            z: self.z.rem_euclid(SIZE) as u8,
         }
     }
-    pub fn as_vec3(&self) -> Vec3{
-        return vec3(self.x as f32, self.y as f32, self.z as f32);
+    pub fn as_vec3(&self) -> Vec3 {
+        vec3(self.x as f32, self.y as f32, self.z as f32)
+    }
+}
+
+impl ChunkCoord {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        ChunkCoord { x, y, z }
     }
 }
+
 impl ChunkVec3 {
     pub fn to_chunk(&self) -> ChunkPos {
-        let data = self.0;
-        let data = data.clamp(Vec3::splat(0.0), Vec3::splat(CHUNK_SIZE as f32 - 0.51)); // small enough to not get rounded to chunk size
-        debug_assert!(data.x.round() < 255.0 && data.x >= 0.0);
-        debug_assert!(data.y.round() < 255.0 && data.y >= 0.0);
-        debug_assert!(data.z.round() < 255.0 && data.z >= 0.0);
-        return ChunkPos::new(data.x.round() as u8, data.y.round() as u8, data.z.round() as u8);
+        let data = self.0.round();
+        ChunkPos::new(data.x as i32, data.y as i32, data.z as i32)
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+impl Chunk {
+    pub fn new() -> Self {
+        Chunk {
+            blocks: [BlockTypeId::AIR; CHUNK_TILE_COUNT],
+            solid: [0; CHUNK_TILE_COUNT / 64],
+        }
+    }
+    pub fn index(local: LocalPos) -> usize {
+        const SIZE: usize = CHUNK_SIZE as usize;
+        (local.x as usize) * SIZE * SIZE + (local.y as usize) * SIZE + (local.z as usize)
+    }
+    pub fn is_solid(&self, local: LocalPos) -> bool {
+        let index = Self::index(local);
+        (self.solid[index / 64] >> (index % 64)) & 1 == 1
+    }
+    pub fn set_solid(&mut self, local: LocalPos, solid: bool) {
+        let index = Self::index(local);
+        if solid {
+            self.solid[index / 64] |= 1 << (index % 64);
+        } else {
+            self.solid[index / 64] &= !(1 << (index % 64));
+        }
+    }
+}
+
+impl WorldLayout {
+    pub fn new(size: ChunkCoord) -> Self {
+        debug_assert!(size.x > 0 && size.y > 0 && size.z > 0);
+        let chunk_count = (size.x * size.y * size.z) as usize;
+        WorldLayout {
+            size,
+            chunks: (0..chunk_count).map(|_| Chunk::new()).collect(),
+            entities: HashMap::new(),
+        }
+    }
+    /// amount of tiles along every axis
+    pub fn size_in_tiles(&self) -> ChunkPos {
+        ChunkPos::from_parts(self.size, LocalPos { x: 0, y: 0, z: 0 })
+    }
+    pub fn max_xyz(&self) -> Vec3 {
+        self.size_in_tiles().as_vec3()
+    }
+    pub fn in_bounds(&self, pos: ChunkPos) -> bool {
+        let max = self.size_in_tiles();
+        pos.x >= 0 && pos.y >= 0 && pos.z >= 0 && pos.x < max.x && pos.y < max.y && pos.z < max.z
+    }
+    /// first and last tile bodies can move through, inclusive. The outer ring along x and z
+    /// and the top layer are the world border
+    pub fn playable_tiles(&self) -> (ChunkPos, ChunkPos) {
+        let max = self.size_in_tiles();
+        (ChunkPos::new(1, 0, 1), ChunkPos::new(max.x - 2, max.y - 2, max.z - 2))
+    }
+    pub fn is_playable(&self, pos: ChunkPos) -> bool {
+        let (min, max) = self.playable_tiles();
+        pos.x >= min.x && pos.y >= min.y && pos.z >= min.z && pos.x <= max.x && pos.y <= max.y && pos.z <= max.z
+    }
+    /// the playable tiles as a box in world space
+    pub fn playable_aabb(&self) -> (Vec3, Vec3) {
+        let (min, max) = self.playable_tiles();
+        (min.as_vec3() - Vec3::splat(0.5), max.as_vec3() + Vec3::splat(0.5))
+    }
+    fn chunk_index(&self, coord: ChunkCoord) -> Option<usize> {
+        if
+            coord.x < 0 ||
+            coord.y < 0 ||
+            coord.z < 0 ||
+            coord.x >= self.size.x ||
+            coord.y >= self.size.y ||
+            coord.z >= self.size.z
+        {
+            return None;
+        }
+        Some((coord.x * self.size.y * self.size.z + coord.y * self.size.z + coord.z) as usize)
+    }
+    pub fn chunk(&self, coord: ChunkCoord) -> Option<&Chunk> {
+        self.chunk_index(coord).map(|i| &self.chunks[i])
+    }
+    /// out of bounds tiles are never solid
+    pub fn is_solid(&self, pos: ChunkPos) -> bool {
+        self.chunk(pos.chunk()).is_some_and(|chunk| chunk.is_solid(pos.local()))
+    }
+    /// `None` for air and out of bounds tiles
+    pub fn block_at(&self, pos: ChunkPos) -> Option<BlockTypeId> {
+        let chunk = self.chunk(pos.chunk())?;
+        let b_type = chunk.blocks[Chunk::index(pos.local())];
+        (b_type != BlockTypeId::AIR).then_some(b_type)
+    }
+    /// solidity is cached in the chunk bitset, so collision checks dont need to go through the registry
+    pub fn set_block(&mut self, pos: ChunkPos, b_type: BlockTypeId, registry: &BlockRegistry) {
+        if let Some(index) = self.chunk_index(pos.chunk()) {
+            let chunk = &mut self.chunks[index];
+            chunk.blocks[Chunk::index(pos.local())] = b_type;
+            chunk.set_solid(pos.local(), registry.get(b_type).solid);
+        }
+    }
+    pub fn clear_block(&mut self, pos: ChunkPos) {
+        if let Some(index) = self.chunk_index(pos.chunk()) {
+            let chunk = &mut self.chunks[index];
+            chunk.blocks[Chunk::index(pos.local())] = BlockTypeId::AIR;
+            chunk.set_solid(pos.local(), false);
+        }
+    }
+    /// height of the ramp surface under `pos`, ramps in the tile of `pos` and the ones right above and below count
+    pub fn ramp_surface(&self, pos: Vec3, registry: &BlockRegistry) -> Option<(f32, BlockTypeId)> {
+        let tile = ChunkVec3(pos).to_chunk();
+        [1, 0, -1].into_iter().find_map(|dy| {
+            let tile = ChunkPos::new(tile.x, tile.y + dy, tile.z);
+            let b_type = self.block_at(tile)?;
+            let facing = registry.get(b_type).ramp?;
+            let along = (pos - tile.as_vec3()).dot(facing.dir()) + 0.5;
+            Some(((tile.y as f32) - 0.5 + along.clamp(0.0, 1.0), b_type))
+        })
+    }
+    /// dynamic entities and interactables in a tile, blocks are tracked by `block_at`
+    pub fn entities_at(&self, pos: ChunkPos) -> &[EntityType] {
+        self.entities.get(&pos).map(|tile| tile.as_slice()).unwrap_or(&[])
+    }
+    /// every tile touched by the box, tiles are centered on integer coordinates
+    pub fn tiles_in_aabb(min: Vec3, max: Vec3) -> impl Iterator<Item = ChunkPos> {
+        let start = ChunkVec3(min).to_chunk();
+        let end = ChunkVec3(max).to_chunk();
+        (start.x..=end.x).flat_map(move |x| {
+            (start.y..=end.y).flat_map(move |y| {
+                (start.z..=end.z).map(move |z| ChunkPos::new(x, y, z))
+            })
+        })
+    }
+    /// unique entities whose occupied tiles overlap the box
+    pub fn entities_in_aabb(&self, min: Vec3, max: Vec3) -> Vec<EntityType> {
+        let mut res = Vec::new();
+        for tile in Self::tiles_in_aabb(min, max) {
+            for entity in self.entities_at(tile) {
+                if !res.contains(entity) {
+                    res.push(*entity);
+                }
+            }
+        }
+        res
+    }
+    /// out of bounds positions are ignored
+    pub fn insert(&mut self, pos: ChunkPos, entity: EntityType) {
+        if !self.in_bounds(pos) {
+            return;
+        }
+        self.entities.entry(pos).or_default().push(entity);
+    }
+    /// every in bounds tile the hitbox touches, the way the movement system keeps entities in the layout
+    pub fn insert_aabb(&mut self, pos: ChunkVec3, half_hitbox: Vec3, entity: EntityType) {
+        for tile in Self::tiles_in_aabb(pos.0 - half_hitbox, pos.0 + half_hitbox) {
+            self.insert(tile, entity);
+        }
+    }
+    pub fn remove(&mut self, pos: ChunkPos, entity: EntityType) {
+        self.retain(pos, |e| *e != entity);
+    }
+    pub fn retain<F: FnMut(&EntityType) -> bool>(&mut self, pos: ChunkPos, f: F) {
+        if let Some(tile) = self.entities.get_mut(&pos) {
+            tile.retain(f);
+            if tile.is_empty() {
+                self.entities.remove(&pos);
+            }
+        }
+    }
+
+    /// compares the occupancy and the chunk blocks against the arrays they are derived from,
+    /// an empty result means the layout is consistent. Slow, meant for tests and the debug overlay
+    pub fn check_consistency(
+        &self,
+        player: &Player,
+        enemies: &Enemies,
+        solid_blocks: &SolidBlocks,
+        interactable_blocks: &InteractableBlocks,
+        registry: &BlockRegistry,
+    ) -> Vec<LayoutIssue> {
+        let mut issues = Vec::new();
+        let enemy_count = enemies.bodies.len();
+        if [
+            enemies.animation_state.len(),
+            enemies.size.len(),
+            enemies.healths.len(),
+            enemies.e_type.len(),
+        ]
+        .iter()
+        .any(|&len| len != enemy_count)
+        {
+            issues.push(LayoutIssue::ArrayLengthMismatch("enemies"));
+        }
+        if solid_blocks.b_types.len() != solid_blocks.positions.len()
+            || solid_blocks.hit_points.len() != solid_blocks.positions.len()
+        {
+            issues.push(LayoutIssue::ArrayLengthMismatch("solid blocks"));
+        }
+        if interactable_blocks.b_types.len() != interactable_blocks.positions.len()
+            || interactable_blocks.exits.len() != interactable_blocks.positions.len()
+        {
+            issues.push(LayoutIssue::ArrayLengthMismatch("interactable blocks"));
+        }
+
+        // where every live entity should be listed according to its position and hitbox
+        let mut expected: HashMap<ChunkPos, Vec<EntityType>> = HashMap::new();
+        let mut expect = |tile: ChunkPos, entity: EntityType| {
+            if self.in_bounds(tile) {
+                expected.entry(tile).or_default().push(entity);
+            }
+        };
+        for tile in Self::tiles_in_aabb(player.body.min(), player.body.max()) {
+            expect(tile, EntityType::Player);
+        }
+        for (i, body) in enemies.bodies.iter().enumerate() {
+            if enemies.e_type.get(i).is_none_or(|e_type| *e_type == EnemyType::Empty) {
+                continue;
+            }
+            for tile in Self::tiles_in_aabb(body.min(), body.max()) {
+                expect(tile, EntityType::Enemy(EnemyHandle(i as u16)));
+            }
+        }
+        for (i, pos) in interactable_blocks.positions.iter().enumerate() {
+            expect(pos.to_chunk(), EntityType::InteractableBlock(i as u16));
+        }
+
+        for (&tile, entities) in &self.entities {
+            let wanted = expected.get(&tile).map(|e| e.as_slice()).unwrap_or(&[]);
+            for (i, entity) in entities.iter().enumerate() {
+                let stale = match entity {
+                    EntityType::Player => false,
+                    EntityType::Enemy(h) =>
+                        enemies.e_type.get(h.0 as usize).is_none_or(|e_type| *e_type == EnemyType::Empty),
+                    EntityType::InteractableBlock(index) => *index as usize >= interactable_blocks.positions.len(),
+                };
+                if stale {
+                    issues.push(LayoutIssue::StaleHandle(tile, *entity));
+                } else if entities[..i].contains(entity) {
+                    issues.push(LayoutIssue::DuplicateOccupancy(tile, *entity));
+                } else if !wanted.contains(entity) {
+                    issues.push(LayoutIssue::ExtraOccupancy(tile, *entity));
+                }
+            }
+        }
+        for (tile, entities) in &expected {
+            for entity in entities {
+                if !self.entities_at(*tile).contains(entity) {
+                    issues.push(LayoutIssue::MissingOccupancy(*tile, *entity));
+                }
+            }
+        }
+
+        let mut listed = HashMap::new();
+        for (pos, b_type) in solid_blocks.positions.iter().zip(solid_blocks.b_types.iter()) {
+            let tile = pos.to_chunk();
+            listed.insert(tile, *b_type);
+            if self.block_at(tile) != Some(*b_type) {
+                issues.push(LayoutIssue::MissingBlock(tile));
+            }
+        }
+        for (chunk_index, chunk) in self.chunks.iter().enumerate() {
+            let chunk_index = chunk_index as i32;
+            let coord = ChunkCoord::new(
+                chunk_index / (self.size.y * self.size.z),
+                (chunk_index / self.size.z) % self.size.y,
+                chunk_index % self.size.z,
+            );
+            for x in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_SIZE {
+                    for z in 0..CHUNK_SIZE {
+                        let local = LocalPos { x, y, z };
+                        let b_type = chunk.blocks[Chunk::index(local)];
+                        let tile = ChunkPos::from_parts(coord, local);
+                        if chunk.is_solid(local) != registry.get(b_type).solid {
+                            issues.push(LayoutIssue::SolidMismatch(tile));
+                        }
+                        if b_type != BlockTypeId::AIR && !listed.contains_key(&tile) {
+                            issues.push(LayoutIssue::ExtraBlock(tile));
+                        }
+                    }
+                }
+            }
+        }
+        issues
+    }
+}
 
 impl CustomCamera3D {
     pub fn new(camera: Camera3D, depth_enabled: bool) -> Self {
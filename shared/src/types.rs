@@ -1,14 +1,51 @@
-use macroquad::{camera::Camera3D, color::Color, math::Vec3};
+use std::collections::HashMap;
+
+use macroquad::{camera::Camera3D, color::Color, math::{Vec2, Vec3}};
+
+use crate::config::CHUNK_SIZE;
 #[derive(Clone, Copy, Debug)]
 pub struct ChunkVec3(pub Vec3);
 
-#[derive(Debug, Clone, Copy)]
+/// Global tile position, split it with `chunk()` and `local()` to address a tile inside its chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ChunkPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// Position of a chunk in the world, measured in chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// Offset of a tile inside its chunk, every component is < CHUNK_SIZE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalPos {
     pub x: u8,
     pub y: u8,
     pub z: u8,
 }
 
+pub const CHUNK_TILE_COUNT: usize = (CHUNK_SIZE as usize) * (CHUNK_SIZE as usize) * (CHUNK_SIZE as usize);
+
+/// block type of every tile plus one bit per tile that is set if the block is solid
+pub struct Chunk {
+    pub blocks: [BlockTypeId; CHUNK_TILE_COUNT],
+    pub solid: [u64; CHUNK_TILE_COUNT / 64],
+}
+
+/// Occupancy of the world, static solids live in the chunk bitsets,
+/// everything else (player, enemies, interactables) in a spatial hash keyed by tile
+pub struct WorldLayout {
+    pub size: ChunkCoord,
+    pub chunks: Vec<Chunk>,
+    pub entities: HashMap<ChunkPos, Vec<EntityType>>,
+}
+
 #[derive(Clone, Copy)]
 pub enum WeaponType {
     Shotgun,
@@ -25,14 +62,71 @@ pub struct Weapon {
 pub struct CurrWeapon(pub usize);
 pub struct MaxWeapon(pub usize);
 pub struct Player {
-    pub pos: ChunkVec3,
-    pub vel: Vec3,
+    pub body: Body,
     pub yaw: f32,
     pub pitch: f32,
     pub weapon_unlocked: MaxWeapon,
     pub weapons: [Weapon; 1],
     pub curr_weapon: CurrWeapon,
     pub animation_state: AnimationState,
+    pub health: f32,
+    pub jump: JumpState,
+    pub input: MoveInput,
+    pub crouching: bool, // can stay true after letting go of crouch while there is no room to stand up
+    pub dash: DashState,
+}
+
+/// anything that moves through the world, the movement system integrates and collides all of them the same way
+#[derive(Clone, Copy, Debug)]
+pub struct Body {
+    pub pos: ChunkVec3, // center of the hitbox
+    pub prev_pos: ChunkVec3, // where the last physics step started, rendering interpolates from here
+    pub vel: Vec3, // tiles per second
+    pub half_extents: Vec3,
+    pub mass: f32, // impulses get divided by it
+    pub grounded: bool,
+}
+
+/// short burst along the wish direction, the timers count down in seconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DashState {
+    pub requested: bool,
+    pub dir: Vec3,
+    pub time_left: f32,
+    pub cooldown: f32,
+    pub invulnerable: f32,
+}
+
+/// everything the player did during one physics step, the simulation reads nothing else from the keyboard or mouse.
+/// Pressed flags only count for the step they got collected for, held ones stay set while the key is down
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TickInput {
+    pub movement: Vec2, // x strafes right, y walks forward, in -1..=1
+    pub look: Vec2, // radians added to yaw and pitch
+    pub jump_pressed: bool,
+    pub jump_held: bool,
+    pub sprint: bool,
+    pub crouch: bool,
+    pub dash: bool,
+    pub shoot: bool,
+    pub next_weapon: bool,
+}
+
+/// movement the player asks for, taken from the TickInput and applied by the movement system
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MoveInput {
+    pub wish_dir: Vec3, // horizontal, zero or normalized
+    pub sprint: bool,
+    pub crouch: bool,
+}
+
+/// jump input and timers, kept between physics frames by the movement system. Ground contact is on the body
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JumpState {
+    pub since_grounded: f32, // seconds, jumping still works for COYOTE_TIME after walking off a ledge
+    pub buffered: f32, // seconds left until a jump press that came too early is dropped
+    pub held: bool,
+    pub rising: bool, // going up from a jump, letting go of the key cuts it short
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -41,19 +135,30 @@ pub struct EnemyHandle(pub u16);
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum EntityType {
-    SolidBlock,
     Player,
     InteractableBlock(u16),
     Enemy(EnemyHandle),
 }
-enum InteractableBlockType {
-    Portal,
-    Trampoline,
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InteractableBlockType {
+    Portal { link: u8, facing: Facing }, // the two portals sharing a link id lead into each other
+    Trampoline(f32), // launch height in tiles
+    Stairs, // leads down to the next floor once every room is cleared
+}
+
+/// side of a block that entities leave a portal through, or that a ramp rises towards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Facing {
+    PosX,
+    NegX,
+    PosZ,
+    NegZ,
 }
 
 pub struct InteractableBlocks {
-    pub positions: Vec<Vec3>,
-    pub b_type: InteractableBlockType,
+    pub positions: Vec<ChunkVec3>,
+    pub b_types: Vec<InteractableBlockType>,
+    pub exits: Vec<Option<u16>>, // linked portal of every portal, filled by `link_portals`
 }
 #[derive(PartialEq, Clone, Copy)]
 pub enum AnimationCallbackEvent {
@@ -66,7 +171,7 @@ pub struct AnimationState {
     pub callback: AnimationCallbackEvent,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PossibleEnemySizes {
     SMALL,
     MEDIUM,
@@ -82,8 +187,7 @@ pub enum EnemyType {
 }
 
 pub struct Enemies {
-    pub positions: Vec<ChunkVec3>,
-    pub velocities: Vec<Vec3>,
+    pub bodies: Vec<Body>,
     pub animation_state: Vec<AnimationState>,
     pub size: Vec<PossibleEnemySizes>,
     pub healths: Vec<u8>,
@@ -92,6 +196,44 @@ pub struct Enemies {
 
 pub struct SolidBlocks {
     pub positions: Vec<ChunkVec3>,
+    pub b_types: Vec<BlockTypeId>,
+    pub hit_points: Vec<u8>,
+}
+
+/// index into the `BlockRegistry`, `BlockTypeId::AIR` marks an empty tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockTypeId(pub u8);
+
+#[derive(Clone, Copy, Debug)]
+pub struct BlockProperties {
+    pub name: &'static str,
+    pub solid: bool,
+    pub destructible: bool,
+    pub hit_points: u8,
+    pub color: Color,
+    pub friction: f32,
+    pub damage_on_touch: f32, // per second
+    pub ramp: Option<Facing>, // ramps are not solid, whoever walks into one gets lifted onto its slope
+}
+
+pub struct BlockRegistry {
+    pub blocks: Vec<BlockProperties>,
+}
+
+/// small deterministic generator (SplitMix64), the same seed always gives the same sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    pub state: u64,
+}
+
+/// everything random in a run draws from here, one stream per system so that e.g. a new loot roll
+/// does not change where the next enemy spawns. The whole run follows from `seed`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunRng {
+    pub seed: u64,
+    pub spawning: Rng,
+    pub loot: Rng, // nothing drops loot yet
+    pub ai: Rng, // enemy steering does not roll anything yet
 }
 
 #[derive(Hash, Eq, PartialEq, Copy, Clone)]
@@ -104,6 +246,24 @@ pub enum Textures {
 pub enum WorldEvent {
     KillEnemy(EnemyHandle),
     HitEnemy(EnemyHandle),
+    DamagePlayer(f32),
+    BlockDamaged(ChunkPos),
+    BlockDestroyed(ChunkPos),
+    Explosion(ChunkVec3, f32), // center, radius in tiles
+    Impulse(EntityType, Vec3), // change in velocity times the mass of the body
+}
+
+/// a place where `WorldLayout` disagrees with the entity and block arrays
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutIssue {
+    StaleHandle(ChunkPos, EntityType), // points past the end of its array or at an empty enemy slot
+    MissingOccupancy(ChunkPos, EntityType), // the hitbox covers the tile, but the tile doesnt list the entity
+    ExtraOccupancy(ChunkPos, EntityType), // listed in a tile its hitbox doesnt cover
+    DuplicateOccupancy(ChunkPos, EntityType),
+    MissingBlock(ChunkPos), // in SolidBlocks, but the chunk has a different block there
+    ExtraBlock(ChunkPos), // in the chunk, but not in SolidBlocks
+    SolidMismatch(ChunkPos), // solid bit doesnt match the block registry
+    ArrayLengthMismatch(&'static str),
 }
 
 #[derive(Clone, Copy)]
@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+
+use shared::{
+    config::CHUNK_SIZE,
+    types::{ BlockTypeId, ChunkCoord, ChunkPos, Facing, Rng },
+    Vec3,
+};
+
+use crate::level::{ Level, LevelMeta };
+
+const MAX_CLIMB: i32 = 1; // highest step the player can jump onto
+const START_CLEARING: i32 = 3; // tiles around the player start that stay flat
+const RAMPS: [BlockTypeId; 4] = [
+    BlockTypeId::RAMP_POS_X,
+    BlockTypeId::RAMP_NEG_X,
+    BlockTypeId::RAMP_POS_Z,
+    BlockTypeId::RAMP_NEG_Z,
+];
+const HEADROOM: i32 = 4; // free tiles above the highest block, so spawned enemies have room to fall in
+
+pub struct ArenaConfig {
+    pub size: ChunkCoord,
+    pub pillars: usize,
+    pub platforms: usize,
+    pub cover: usize,
+    pub flat_areas: Vec<[i32; 4]>, // x0 z0 x1 z1 inclusive, stay at floor height next to the start clearing
+}
+
+/// Arenas are built as a heightmap, every column is the stone floor with a stack of one block type on top.
+/// The outermost ring of tiles is the world border and stays flat
+pub struct ArenaGenerator {
+    rng: Rng,
+    width: i32,
+    depth: i32,
+    max_height: i32,
+    heights: Vec<i32>, // tiles stacked on top of the floor
+    b_types: Vec<BlockTypeId>,
+    start: (i32, i32),
+    flat_areas: Vec<[i32; 4]>,
+}
+
+impl ArenaGenerator {
+    pub fn generate(seed: u64, config: &ArenaConfig) -> Level {
+        let width = config.size.x * (CHUNK_SIZE as i32);
+        let depth = config.size.z * (CHUNK_SIZE as i32);
+        let column_count = (width * depth) as usize;
+        let mut generator = ArenaGenerator {
+            rng: Rng::new(seed),
+            width,
+            depth,
+            max_height: config.size.y * (CHUNK_SIZE as i32) - HEADROOM - 1,
+            heights: vec![0; column_count],
+            b_types: vec![BlockTypeId::STONE; column_count],
+            start: (width / 2, depth / 2),
+            flat_areas: config.flat_areas.clone(),
+        };
+        for _ in 0..config.platforms {
+            generator.place_platform();
+        }
+        for _ in 0..config.pillars {
+            generator.place_pillar();
+        }
+        for _ in 0..config.cover {
+            generator.place_cover();
+        }
+        generator.ensure_reachable();
+        generator.into_level(seed, config.size)
+    }
+
+    fn index(&self, x: i32, z: i32) -> usize {
+        (x * self.depth + z) as usize
+    }
+
+    fn is_interior(&self, x: i32, z: i32) -> bool {
+        x >= 1 && z >= 1 && x < self.width - 1 && z < self.depth - 1
+    }
+
+    fn in_clearing(&self, x: i32, z: i32) -> bool {
+        let near_start = (x - self.start.0).abs() <= START_CLEARING && (z - self.start.1).abs() <= START_CLEARING;
+        near_start ||
+            self.flat_areas
+                .iter()
+                .any(|&[x0, z0, x1, z1]| x >= x0 && x <= x1 && z >= z0 && z <= z1)
+    }
+
+    /// stacks the column up to `height`, never lowers it
+    fn raise(&mut self, x: i32, z: i32, height: i32, b_type: BlockTypeId) {
+        if !self.is_interior(x, z) || self.in_clearing(x, z) {
+            return;
+        }
+        let index = self.index(x, z);
+        let height = height.min(self.max_height);
+        if height > self.heights[index] {
+            self.heights[index] = height;
+            self.b_types[index] = b_type;
+        }
+    }
+
+    /// raised rectangle with a staircase leading up one of its sides
+    fn place_platform(&mut self) {
+        let w = self.rng.gen_range(4, 9);
+        let d = self.rng.gen_range(4, 9);
+        let height = self.rng.gen_range(2, 5);
+        let x0 = self.rng.gen_range(1, self.width - 1 - w);
+        let z0 = self.rng.gen_range(1, self.depth - 1 - d);
+        for x in x0..x0 + w {
+            for z in z0..z0 + d {
+                self.raise(x, z, height, BlockTypeId::STONE);
+            }
+        }
+
+        // two tiles wide, every step one tile lower than the one before and topped with a ramp up to the next one
+        let (dir_x, dir_z, stair_x, stair_z, up) = match self.rng.gen_range(0, 4) {
+            0 => (-1, 0, x0, z0 + d / 2, Facing::PosX),
+            1 => (1, 0, x0 + w - 1, z0 + d / 2, Facing::NegX),
+            2 => (0, -1, x0 + w / 2, z0, Facing::PosZ),
+            _ => (0, 1, x0 + w / 2, z0 + d - 1, Facing::NegZ),
+        };
+        let ramp = BlockTypeId::ramp(up);
+        for step in 1..=height {
+            let x = stair_x + dir_x * step;
+            let z = stair_z + dir_z * step;
+            self.raise(x, z, height - step + 1, ramp);
+            self.raise(x + dir_z.abs(), z + dir_x.abs(), height - step + 1, ramp);
+        }
+    }
+
+    fn place_pillar(&mut self) {
+        let thickness = self.rng.gen_range(1, 3);
+        let height = self.rng.gen_range(3, 8);
+        let x0 = self.rng.gen_range(1, self.width - 1 - thickness);
+        let z0 = self.rng.gen_range(1, self.depth - 1 - thickness);
+        for x in x0..x0 + thickness {
+            for z in z0..z0 + thickness {
+                self.raise(x, z, height, BlockTypeId::STONE);
+            }
+        }
+    }
+
+    /// short breakable wall to hide behind
+    fn place_cover(&mut self) {
+        let length = self.rng.gen_range(3, 7);
+        let height = self.rng.gen_range(1, 3);
+        let b_type = if self.rng.chance(0.7) { BlockTypeId::CRATE } else { BlockTypeId::GLASS };
+        let along_x = self.rng.chance(0.5);
+        let x0 = self.rng.gen_range(1, self.width - 1 - length);
+        let z0 = self.rng.gen_range(1, self.depth - 1 - length);
+        for i in 0..length {
+            let (x, z) = if along_x { (x0 + i, z0) } else { (x0, z0 + i) };
+            self.raise(x, z, height, b_type);
+        }
+    }
+
+    /// interior columns the player start can be reached from, walking down is always possible,
+    /// walking up only by MAX_CLIMB tiles at a time
+    fn reachable_from_start(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.heights.len()];
+        let mut queue = VecDeque::new();
+        reached[self.index(self.start.0, self.start.1)] = true;
+        queue.push_back(self.start);
+        while let Some((x, z)) = queue.pop_front() {
+            let height = self.heights[self.index(x, z)];
+            for (nx, nz) in [(x - 1, z), (x + 1, z), (x, z - 1), (x, z + 1)] {
+                if !self.is_interior(nx, nz) {
+                    continue;
+                }
+                let index = self.index(nx, nz);
+                if !reached[index] && height - self.heights[index] <= MAX_CLIMB {
+                    reached[index] = true;
+                    queue.push_back((nx, nz));
+                }
+            }
+        }
+        reached
+    }
+
+    /// raises columns that are stuck in a pit next to the reachable area until the whole arena connects,
+    /// every pass grows the reachable area by at least one column
+    fn ensure_reachable(&mut self) {
+        loop {
+            let reached = self.reachable_from_start();
+            let mut changed = false;
+            for x in 1..self.width - 1 {
+                for z in 1..self.depth - 1 {
+                    let index = self.index(x, z);
+                    if reached[index] {
+                        continue;
+                    }
+                    let neighbour_height = [(x - 1, z), (x + 1, z), (x, z - 1), (x, z + 1)]
+                        .into_iter()
+                        .filter(|&(nx, nz)| self.is_interior(nx, nz) && reached[self.index(nx, nz)])
+                        .map(|(nx, nz)| self.heights[self.index(nx, nz)])
+                        .min();
+                    if let Some(neighbour_height) = neighbour_height {
+                        if self.heights[index] == 0 {
+                            self.b_types[index] = BlockTypeId::STONE;
+                        }
+                        self.heights[index] = neighbour_height - MAX_CLIMB;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn into_level(self, seed: u64, size: ChunkCoord) -> Level {
+        let mut solid_blocks = Vec::new();
+        for x in 0..self.width {
+            for z in 0..self.depth {
+                let index = self.index(x, z);
+                solid_blocks.push((ChunkPos::new(x, 0, z), BlockTypeId::STONE));
+                let height = self.heights[index];
+                for y in 1..=height {
+                    // ramps only make sense on top, the rest of the column is stone
+                    let b_type = if y < height && RAMPS.contains(&self.b_types[index]) {
+                        BlockTypeId::STONE
+                    } else {
+                        self.b_types[index]
+                    };
+                    solid_blocks.push((ChunkPos::new(x, y, z), b_type));
+                }
+            }
+        }
+        Level {
+            meta: LevelMeta {
+                name: format!("Arena {}", seed),
+                author: String::new(),
+                seed: Some(seed),
+                floor: 0,
+            },
+            size,
+            player_start: Vec3::new(self.start.0 as f32, 1.0, self.start.1 as f32),
+            solid_blocks,
+            interactable_blocks: Vec::new(),
+            enemy_spawns: Vec::new(),
+            rooms: Vec::new(),
+        }
+    }
+}
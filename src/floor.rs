@@ -0,0 +1,152 @@
+use shared::{
+    config::CHUNK_SIZE,
+    types::{ BlockTypeId, ChunkCoord, ChunkPos, ChunkVec3, InteractableBlockType, Rng },
+    Vec3,
+};
+
+use crate::{ arena::{ ArenaConfig, ArenaGenerator }, level::{ Level, LevelMeta } };
+
+const ROOM_SIZE: i32 = CHUNK_SIZE as i32; // every room fills one chunk
+const WALL_HEIGHT: i32 = ROOM_SIZE - 2; // up to the world ceiling, so nobody jumps over from a pillar
+const DOOR_HEIGHT: i32 = 2;
+const DOOR_CENTER: i32 = ROOM_SIZE / 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoomKind {
+    Start,
+    Combat,
+    Boss,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoomState {
+    Idle, // not entered yet
+    Locked, // player inside, doors stay shut until every enemy is dead
+    Cleared,
+}
+
+#[derive(Clone, Debug)]
+pub struct Room {
+    pub kind: RoomKind,
+    pub state: RoomState,
+    pub min: ChunkPos, // walkable tiles inside the walls, inclusive
+    pub max: ChunkPos,
+    pub doors: Vec<ChunkPos>, // tiles that get filled with door blocks while the room is locked
+}
+
+impl Room {
+    pub fn contains(&self, pos: ChunkVec3) -> bool {
+        let tile = pos.to_chunk();
+        tile.x >= self.min.x && tile.x <= self.max.x && tile.z >= self.min.z && tile.z <= self.max.z
+    }
+    /// true once the whole box is past the doorway, so doors never close on top of it
+    pub fn encloses(&self, pos: ChunkVec3, half_extents: Vec3) -> bool {
+        self.contains(ChunkVec3(pos.0 - half_extents)) && self.contains(ChunkVec3(pos.0 + half_extents))
+    }
+}
+
+/// Floors are a row of rooms along x, a start room, a few combat rooms and a boss room at the end
+/// with the stairs down to the next floor
+pub struct FloorGenerator;
+
+impl FloorGenerator {
+    /// the same run seed and floor number always give the same floor
+    pub fn generate(run_seed: u64, floor: u32) -> Level {
+        let mut rng = Rng::new(run_seed ^ (floor as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let combat_rooms = 2 + (floor as i32).min(3);
+        let room_count = combat_rooms + 2;
+
+        let mut solid_blocks = Vec::new();
+        let mut interactable_blocks = Vec::new();
+        let mut rooms = Vec::new();
+        for i in 0..room_count {
+            let kind = match i {
+                0 => RoomKind::Start,
+                _ if i == room_count - 1 => RoomKind::Boss,
+                _ => RoomKind::Combat,
+            };
+            let (pillars, platforms, cover) = match kind {
+                RoomKind::Start => (2, 0, 2),
+                RoomKind::Combat => (rng.gen_range(2, 5), rng.gen_range(0, 2), rng.gen_range(3, 6)),
+                RoomKind::Boss => (4, 1, 2),
+            };
+            let config = ArenaConfig {
+                size: ChunkCoord::new(1, 1, 1),
+                pillars: pillars as usize,
+                platforms: platforms as usize,
+                cover: cover as usize,
+                // flat corridor from door to door, so every room can be crossed both ways
+                flat_areas: vec![[1, DOOR_CENTER - 1, ROOM_SIZE - 2, DOOR_CENTER]],
+            };
+            let x0 = i * ROOM_SIZE;
+            // the outer ring of a generated arena is flat, that is where the walls go
+            let interior = ArenaGenerator::generate(rng.next_u64(), &config);
+            solid_blocks.extend(
+                interior.solid_blocks
+                    .iter()
+                    .map(|&(pos, b_type)| (ChunkPos::new(pos.x + x0, pos.y, pos.z), b_type))
+            );
+
+            let mut doors = Vec::new();
+            if i > 0 {
+                doors.extend(Self::doorway(x0 - 1));
+            }
+            if i < room_count - 1 {
+                doors.extend(Self::doorway(x0 + ROOM_SIZE - 1));
+            }
+            for x in x0..x0 + ROOM_SIZE {
+                for z in 0..ROOM_SIZE {
+                    let on_wall = x == x0 || x == x0 + ROOM_SIZE - 1 || z == 0 || z == ROOM_SIZE - 1;
+                    if !on_wall {
+                        continue;
+                    }
+                    for y in 1..=WALL_HEIGHT {
+                        let pos = ChunkPos::new(x, y, z);
+                        if !doors.contains(&pos) {
+                            solid_blocks.push((pos, BlockTypeId::STONE));
+                        }
+                    }
+                }
+            }
+            if kind == RoomKind::Boss {
+                let stairs = ChunkPos::new(x0 + ROOM_SIZE / 2, 1, ROOM_SIZE / 2);
+                interactable_blocks.push((stairs, InteractableBlockType::Stairs));
+            }
+            rooms.push(Room {
+                kind,
+                state: if kind == RoomKind::Start { RoomState::Cleared } else { RoomState::Idle },
+                min: ChunkPos::new(x0 + 1, 1, 1),
+                max: ChunkPos::new(x0 + ROOM_SIZE - 2, 1, ROOM_SIZE - 2),
+                doors,
+            });
+        }
+
+        Level {
+            meta: LevelMeta {
+                name: format!("Floor {}", floor),
+                author: String::new(),
+                seed: Some(run_seed),
+                floor,
+            },
+            size: ChunkCoord::new(room_count, 1, 1),
+            player_start: Vec3::new((ROOM_SIZE / 2) as f32, 1.0, (ROOM_SIZE / 2) as f32),
+            solid_blocks,
+            interactable_blocks,
+            enemy_spawns: Vec::new(),
+            rooms,
+        }
+    }
+
+    /// opening between two rooms, both wall layers next to `wall_x` and `wall_x + 1`
+    fn doorway(wall_x: i32) -> Vec<ChunkPos> {
+        let mut tiles = Vec::new();
+        for x in [wall_x, wall_x + 1] {
+            for y in 1..=DOOR_HEIGHT {
+                for z in DOOR_CENTER - 1..=DOOR_CENTER {
+                    tiles.push(ChunkPos::new(x, y, z));
+                }
+            }
+        }
+        tiles
+    }
+}
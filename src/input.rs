@@ -0,0 +1,281 @@
+use std::{ collections::HashMap, fmt, fs, io, path::Path };
+
+use macroquad::input::{ is_key_down, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, KeyCode, MouseButton };
+
+pub const DEFAULT_BINDINGS_PATH: &str = "bindings.cfg";
+
+// Binding files are plain text, one action per line, `#` starts a comment:
+//
+//   <action> <input> [<input> ...]
+//
+// where <input> is a key name (`w`, `space`, `left_shift`, `f5`, see KEYS) or `mouse_left`, `mouse_right`,
+// `mouse_middle`. A listed action loses its default inputs, actions that are not listed keep them.
+// Debug actions only exist in debug builds, release builds skip their lines.
+
+/// everything an input can do, the game asks for actions and never for keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sprint,
+    Crouch,
+    Dash,
+    Shoot,
+    NextWeapon,
+    ToggleCursor,
+    QuickSave,
+    QuickLoad,
+    // debug builds only
+    ReloadLevel,
+    PrintPositions,
+    LayoutOverlay,
+    SaveAndQuit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io(err) => write!(f, "could not read bindings: {}", err),
+            BindingsError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+impl From<io::Error> for BindingsError {
+    fn from(err: io::Error) -> Self {
+        BindingsError::Io(err)
+    }
+}
+
+const ACTIONS: [(Action, &str); 17] = [
+    (Action::MoveForward, "move_forward"),
+    (Action::MoveBack, "move_back"),
+    (Action::MoveLeft, "move_left"),
+    (Action::MoveRight, "move_right"),
+    (Action::Jump, "jump"),
+    (Action::Sprint, "sprint"),
+    (Action::Crouch, "crouch"),
+    (Action::Dash, "dash"),
+    (Action::Shoot, "shoot"),
+    (Action::NextWeapon, "next_weapon"),
+    (Action::ToggleCursor, "toggle_cursor"),
+    (Action::QuickSave, "quick_save"),
+    (Action::QuickLoad, "quick_load"),
+    (Action::ReloadLevel, "reload_level"),
+    (Action::PrintPositions, "print_positions"),
+    (Action::LayoutOverlay, "layout_overlay"),
+    (Action::SaveAndQuit, "save_and_quit"),
+];
+
+const KEYS: [(&str, KeyCode); 62] = [
+    ("a", KeyCode::A),
+    ("b", KeyCode::B),
+    ("c", KeyCode::C),
+    ("d", KeyCode::D),
+    ("e", KeyCode::E),
+    ("f", KeyCode::F),
+    ("g", KeyCode::G),
+    ("h", KeyCode::H),
+    ("i", KeyCode::I),
+    ("j", KeyCode::J),
+    ("k", KeyCode::K),
+    ("l", KeyCode::L),
+    ("m", KeyCode::M),
+    ("n", KeyCode::N),
+    ("o", KeyCode::O),
+    ("p", KeyCode::P),
+    ("q", KeyCode::Q),
+    ("r", KeyCode::R),
+    ("s", KeyCode::S),
+    ("t", KeyCode::T),
+    ("u", KeyCode::U),
+    ("v", KeyCode::V),
+    ("w", KeyCode::W),
+    ("x", KeyCode::X),
+    ("y", KeyCode::Y),
+    ("z", KeyCode::Z),
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("f1", KeyCode::F1),
+    ("f2", KeyCode::F2),
+    ("f3", KeyCode::F3),
+    ("f4", KeyCode::F4),
+    ("f5", KeyCode::F5),
+    ("f6", KeyCode::F6),
+    ("f7", KeyCode::F7),
+    ("f8", KeyCode::F8),
+    ("f9", KeyCode::F9),
+    ("f10", KeyCode::F10),
+    ("f11", KeyCode::F11),
+    ("f12", KeyCode::F12),
+    ("space", KeyCode::Space),
+    ("escape", KeyCode::Escape),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("left_shift", KeyCode::LeftShift),
+    ("right_shift", KeyCode::RightShift),
+    ("left_control", KeyCode::LeftControl),
+    ("right_control", KeyCode::RightControl),
+    ("left_alt", KeyCode::LeftAlt),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+];
+
+impl Action {
+    pub fn is_debug(self) -> bool {
+        matches!(self, Action::ReloadLevel | Action::PrintPositions | Action::LayoutOverlay | Action::SaveAndQuit)
+    }
+
+    fn parse(line: usize, name: &str) -> Result<Action, BindingsError> {
+        ACTIONS.iter()
+            .find(|(_, action_name)| *action_name == name)
+            .map(|(action, _)| *action)
+            .ok_or_else(|| BindingsError::Syntax { line, message: format!("unknown action `{}`", name) })
+    }
+}
+
+impl Input {
+    fn parse(line: usize, name: &str) -> Result<Input, BindingsError> {
+        match name {
+            "mouse_left" => Ok(Input::Mouse(MouseButton::Left)),
+            "mouse_right" => Ok(Input::Mouse(MouseButton::Right)),
+            "mouse_middle" => Ok(Input::Mouse(MouseButton::Middle)),
+            _ =>
+                KEYS.iter()
+                    .find(|(key_name, _)| *key_name == name)
+                    .map(|(_, key)| Input::Key(*key))
+                    .ok_or_else(|| BindingsError::Syntax { line, message: format!("unknown key `{}`", name) }),
+        }
+    }
+
+    fn is_down(self) -> bool {
+        match self {
+            Input::Key(key) => is_key_down(key),
+            Input::Mouse(button) => is_mouse_button_down(button),
+        }
+    }
+
+    fn is_pressed(self) -> bool {
+        match self {
+            Input::Key(key) => is_key_pressed(key),
+            Input::Mouse(button) => is_mouse_button_pressed(button),
+        }
+    }
+}
+
+pub struct Bindings {
+    inputs: HashMap<Action, Vec<Input>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let defaults = [
+            (Action::MoveForward, vec![Input::Key(KeyCode::W), Input::Key(KeyCode::Up)]),
+            (Action::MoveBack, vec![Input::Key(KeyCode::S), Input::Key(KeyCode::Down)]),
+            (Action::MoveLeft, vec![Input::Key(KeyCode::A), Input::Key(KeyCode::Left)]),
+            (Action::MoveRight, vec![Input::Key(KeyCode::D), Input::Key(KeyCode::Right)]),
+            (Action::Jump, vec![Input::Key(KeyCode::Space)]),
+            (Action::Sprint, vec![Input::Key(KeyCode::LeftShift)]),
+            (Action::Crouch, vec![Input::Key(KeyCode::LeftControl), Input::Key(KeyCode::C)]),
+            (Action::Dash, vec![Input::Key(KeyCode::Q)]),
+            (Action::Shoot, vec![Input::Mouse(MouseButton::Left)]),
+            (Action::NextWeapon, vec![Input::Key(KeyCode::E)]),
+            (Action::ToggleCursor, vec![Input::Key(KeyCode::Escape)]),
+            (Action::QuickSave, vec![Input::Key(KeyCode::F5)]),
+            (Action::QuickLoad, vec![Input::Key(KeyCode::F9)]),
+            (Action::ReloadLevel, vec![Input::Key(KeyCode::R)]),
+            (Action::PrintPositions, vec![Input::Key(KeyCode::F)]),
+            (Action::LayoutOverlay, vec![Input::Key(KeyCode::F3)]),
+            (Action::SaveAndQuit, vec![Input::Key(KeyCode::V)]),
+        ];
+        let mut bindings = Bindings { inputs: HashMap::new() };
+        for (action, inputs) in defaults {
+            bindings.bind(action, inputs);
+        }
+        bindings
+    }
+}
+
+impl Bindings {
+    /// a missing file just means nothing got rebound
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bindings, BindingsError> {
+        match fs::read_to_string(path) {
+            Ok(source) => Bindings::parse(&source),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Bindings::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Bindings, BindingsError> {
+        let mut bindings = Bindings::default();
+        for (index, raw_line) in source.lines().enumerate() {
+            let line = index + 1;
+            let content = raw_line.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = content.split_whitespace().collect();
+            let action = Action::parse(line, words[0])?;
+            if words.len() < 2 {
+                return Err(BindingsError::Syntax { line, message: format!("`{}` needs at least one input", words[0]) });
+            }
+            let inputs = words[1..]
+                .iter()
+                .map(|name| Input::parse(line, name))
+                .collect::<Result<Vec<Input>, BindingsError>>()?;
+            bindings.bind(action, inputs);
+        }
+        Ok(bindings)
+    }
+
+    fn bind(&mut self, action: Action, inputs: Vec<Input>) {
+        if action.is_debug() && !cfg!(debug_assertions) {
+            return;
+        }
+        self.inputs.insert(action, inputs);
+    }
+
+    fn inputs(&self, action: Action) -> &[Input] {
+        self.inputs.get(&action).map_or(&[], |inputs| inputs.as_slice())
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.inputs(action).iter().any(|input| input.is_down())
+    }
+
+    /// only in the frame the action started
+    pub fn pressed(&self, action: Action) -> bool {
+        self.inputs(action).iter().any(|input| input.is_pressed())
+    }
+}
@@ -0,0 +1,438 @@
+use std::{ collections::HashSet, fmt, fs, path::Path, str::FromStr };
+
+use shared::{
+    config::{ MAX_WORLD_CHUNKS, PLAYER_HALF_HITBOX, TRAMPOLINE_DEFAULT_HEIGHT },
+    types::{
+        BlockRegistry,
+        BlockTypeId,
+        ChunkCoord,
+        ChunkPos,
+        Enemies,
+        EnemyType,
+        Facing,
+        InteractableBlockType,
+        LocalPos,
+        Player,
+        PossibleEnemySizes,
+        WorldLayout,
+    },
+    Vec3,
+};
+
+use crate::{ floor::Room, vox_level::VoxPalette };
+
+pub const DEFAULT_PALETTE_PATH: &str = "levels/default.palette";
+
+// Level files are plain text, one directive per line, `#` starts a comment:
+//
+//   name <text>                      arena name shown to the player
+//   author <text>                    optional
+//   size <x> <y> <z>                 world size in chunks, required, MAX_WORLD_CHUNKS at most
+//   player <x> <y> <z>               player start, required
+//   solid <x> <y> <z> [block type]   single block, `stone` if no type is given
+//   fill <x0> <y0> <z0> <x1> <y1> <z1> [block type]   inclusive box of blocks
+//   trampoline <x> <y> <z> [height]  launches whoever lands on it, height in tiles
+//   portal <x> <y> <z> <link> <+x|-x|+z|-z>   exactly two portals per link id, exits through the given side
+//   enemy <regular|flying|cube> <small|medium|large|boss> <x> <y> <z>
+//
+// Block coordinates are tiles, player and enemy positions may be fractional.
+
+#[derive(Clone, Debug, Default)]
+pub struct LevelMeta {
+    pub name: String,
+    pub author: String,
+    pub seed: Option<u64>, // generated arenas and floors
+    pub floor: u32, // 0 outside of floor runs
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnemySpawn {
+    pub pos: Vec3,
+    pub size: PossibleEnemySizes,
+    pub e_type: EnemyType,
+}
+
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub meta: LevelMeta,
+    pub size: ChunkCoord,
+    pub player_start: Vec3,
+    pub solid_blocks: Vec<(ChunkPos, BlockTypeId)>,
+    pub interactable_blocks: Vec<(ChunkPos, InteractableBlockType)>,
+    pub enemy_spawns: Vec<EnemySpawn>,
+    pub rooms: Vec<Room>, // empty for single arenas, those spawn enemies endlessly
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Vox(String),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    Missing(&'static str),
+    InvalidSize(ChunkCoord),
+    OutOfBounds {
+        line: usize,
+        pos: ChunkPos,
+    },
+    BlockOverlap {
+        line: usize,
+        pos: ChunkPos,
+    },
+    PlayerStartOverlap {
+        line: usize,
+        pos: ChunkPos,
+    },
+    EnemySpawnOverlap {
+        line: usize,
+        pos: ChunkPos,
+    },
+    PortalLink {
+        link: u8,
+        count: usize,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(err) => write!(f, "could not read level: {}", err),
+            LevelError::Vox(message) => write!(f, "invalid vox arena: {}", message),
+            LevelError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            LevelError::Missing(directive) => write!(f, "missing `{}` directive", directive),
+            LevelError::InvalidSize(size) =>
+                write!(
+                    f,
+                    "invalid world size {} {} {}, needs at least one chunk per axis and {} at most",
+                    size.x,
+                    size.y,
+                    size.z,
+                    MAX_WORLD_CHUNKS
+                ),
+            LevelError::OutOfBounds { line, pos } =>
+                write!(f, "line {}: {} {} {} is outside of the world", line, pos.x, pos.y, pos.z),
+            LevelError::BlockOverlap { line, pos } =>
+                write!(f, "line {}: {} {} {} already holds a block", line, pos.x, pos.y, pos.z),
+            LevelError::PlayerStartOverlap { line, pos } =>
+                write!(f, "line {}: player start {} {} {} overlaps a block", line, pos.x, pos.y, pos.z),
+            LevelError::EnemySpawnOverlap { line, pos } =>
+                write!(f, "line {}: enemy spawn {} {} {} overlaps a block", line, pos.x, pos.y, pos.z),
+            LevelError::PortalLink { link, count } =>
+                write!(f, "portal link {} needs exactly two portals, found {}", link, count),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<std::io::Error> for LevelError {
+    fn from(err: std::io::Error) -> Self {
+        LevelError::Io(err)
+    }
+}
+
+pub fn parse_args<T: FromStr>(line: usize, args: &[&str], count: usize) -> Result<Vec<T>, LevelError> {
+    if args.len() != count {
+        return Err(LevelError::Syntax {
+            line,
+            message: format!("expected {} arguments, got {}", count, args.len()),
+        });
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse::<T>().map_err(|_| LevelError::Syntax {
+                line,
+                message: format!("`{}` is not a valid number", arg),
+            })
+        })
+        .collect()
+}
+
+/// optional trailing block type argument of `solid` and `fill`
+fn split_block_type<'a>(
+    line: usize,
+    args: &'a [&'a str],
+    count: usize,
+    registry: &BlockRegistry
+) -> Result<(&'a [&'a str], BlockTypeId), LevelError> {
+    if args.len() != count + 1 {
+        return Ok((args, BlockTypeId::STONE));
+    }
+    Ok((&args[..count], parse_block_type(line, args[count], registry)?))
+}
+
+pub fn parse_block_type(line: usize, name: &str, registry: &BlockRegistry) -> Result<BlockTypeId, LevelError> {
+    match registry.find(name) {
+        Some(b_type) if b_type != BlockTypeId::AIR => Ok(b_type),
+        _ => Err(LevelError::Syntax { line, message: format!("unknown block type `{}`", name) }),
+    }
+}
+
+pub fn parse_facing(line: usize, name: &str) -> Result<Facing, LevelError> {
+    match name {
+        "+x" => Ok(Facing::PosX),
+        "-x" => Ok(Facing::NegX),
+        "+z" => Ok(Facing::PosZ),
+        "-z" => Ok(Facing::NegZ),
+        _ => Err(LevelError::Syntax { line, message: format!("unknown portal side `{}`", name) }),
+    }
+}
+
+/// at least one chunk along every axis and not more than MAX_WORLD_CHUNKS in total
+pub fn check_size(size: ChunkCoord) -> Result<(), LevelError> {
+    let axes = [size.x, size.y, size.z];
+    if axes.iter().any(|&axis| axis <= 0 || axis > MAX_WORLD_CHUNKS) {
+        return Err(LevelError::InvalidSize(size));
+    }
+    // every axis fits now, so the product does as well
+    if axes.iter().map(|&axis| axis as i64).product::<i64>() > MAX_WORLD_CHUNKS as i64 {
+        return Err(LevelError::InvalidSize(size));
+    }
+    Ok(())
+}
+
+/// the player stands in the start tile, its hitbox reaches into the tiles above
+pub fn player_start_tiles(player_start: Vec3) -> impl Iterator<Item = ChunkPos> {
+    let standing = Player::standing_in(player_start).0;
+    WorldLayout::tiles_in_aabb(standing - PLAYER_HALF_HITBOX, standing + PLAYER_HALF_HITBOX)
+}
+
+/// every tile the hitbox of an enemy spawned here covers
+pub fn enemy_spawn_tiles(spawn: &EnemySpawn) -> impl Iterator<Item = ChunkPos> {
+    let half = Enemies::get_hitbox_from_size(spawn.size) * 0.5;
+    WorldLayout::tiles_in_aabb(spawn.pos - half, spawn.pos + half)
+}
+
+/// every portal link id has to connect exactly two portals
+pub fn check_portal_links(interactable_blocks: &[(ChunkPos, InteractableBlockType)]) -> Result<(), LevelError> {
+    let mut counts = [0usize; 256];
+    for (_, b_type) in interactable_blocks {
+        if let InteractableBlockType::Portal { link, .. } = b_type {
+            counts[*link as usize] += 1;
+        }
+    }
+    match counts.iter().enumerate().find(|(_, &count)| count != 0 && count != 2) {
+        Some((link, &count)) => Err(LevelError::PortalLink { link: link as u8, count }),
+        None => Ok(()),
+    }
+}
+
+pub fn parse_enemy_type(line: usize, name: &str) -> Result<EnemyType, LevelError> {
+    match name {
+        "regular" => Ok(EnemyType::Regular),
+        "flying" => Ok(EnemyType::Flying),
+        "cube" => Ok(EnemyType::Cube),
+        _ => Err(LevelError::Syntax { line, message: format!("unknown enemy type `{}`", name) }),
+    }
+}
+
+pub fn parse_enemy_size(line: usize, name: &str) -> Result<PossibleEnemySizes, LevelError> {
+    match name {
+        "small" => Ok(PossibleEnemySizes::SMALL),
+        "medium" => Ok(PossibleEnemySizes::MEDIUM),
+        "large" => Ok(PossibleEnemySizes::LARGE),
+        "boss" => Ok(PossibleEnemySizes::BOSS),
+        _ => Err(LevelError::Syntax { line, message: format!("unknown enemy size `{}`", name) }),
+    }
+}
+
+impl Level {
+    pub fn load<P: AsRef<Path>>(path: P, registry: &BlockRegistry) -> Result<Level, LevelError> {
+        let source = fs::read_to_string(path)?;
+        Level::parse(&source, registry)
+    }
+
+    /// .vox arenas use the palette file next to them (`arena.vox` -> `arena.palette`),
+    /// falling back to the default palette
+    pub fn load_any(path: &str, registry: &BlockRegistry) -> Result<Level, LevelError> {
+        let path_ref = Path::new(path);
+        if path_ref.extension().is_some_and(|ext| ext == "vox") {
+            let palette_path = path_ref.with_extension("palette");
+            let palette = if palette_path.exists() {
+                VoxPalette::load(palette_path, registry)?
+            } else if Path::new(DEFAULT_PALETTE_PATH).exists() {
+                VoxPalette::load(DEFAULT_PALETTE_PATH, registry)?
+            } else {
+                VoxPalette::default()
+            };
+            return Level::from_vox(path, &palette);
+        }
+        Level::load(path, registry)
+    }
+
+    pub fn parse(source: &str, registry: &BlockRegistry) -> Result<Level, LevelError> {
+        let mut meta = LevelMeta::default();
+        let mut size = None;
+        let mut player_start = None;
+        // keep the line of every entry around, so validation can point at it
+        let mut solid_blocks = Vec::new();
+        let mut fills = Vec::new(); // only expanded once the corners are known to be inside the world
+        let mut interactable_blocks = Vec::new();
+        let mut enemy_spawns = Vec::new();
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line = index + 1;
+            let content = raw_line.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+            let mut words = content.split_whitespace();
+            let directive = words.next().unwrap_or("");
+            let args: Vec<&str> = words.collect();
+            match directive {
+                "name" => {
+                    meta.name = args.join(" ");
+                }
+                "author" => {
+                    meta.author = args.join(" ");
+                }
+                "size" => {
+                    let v = parse_args::<i32>(line, &args, 3)?;
+                    size = Some(ChunkCoord::new(v[0], v[1], v[2]));
+                }
+                "player" => {
+                    let v = parse_args::<f32>(line, &args, 3)?;
+                    player_start = Some((line, Vec3::new(v[0], v[1], v[2])));
+                }
+                "solid" => {
+                    let (args, b_type) = split_block_type(line, &args, 3, registry)?;
+                    let v = parse_args::<i32>(line, args, 3)?;
+                    solid_blocks.push((line, ChunkPos::new(v[0], v[1], v[2]), b_type));
+                }
+                "fill" => {
+                    let (args, b_type) = split_block_type(line, &args, 6, registry)?;
+                    let v = parse_args::<i32>(line, args, 6)?;
+                    let min = ChunkPos::new(v[0].min(v[3]), v[1].min(v[4]), v[2].min(v[5]));
+                    let max = ChunkPos::new(v[0].max(v[3]), v[1].max(v[4]), v[2].max(v[5]));
+                    fills.push((line, min, max, b_type));
+                }
+                "trampoline" => {
+                    let (args, height) = match args.len() {
+                        4 => (&args[..3], parse_args::<f32>(line, &args[3..], 1)?[0]),
+                        _ => (&args[..], TRAMPOLINE_DEFAULT_HEIGHT),
+                    };
+                    let v = parse_args::<i32>(line, args, 3)?;
+                    let b_type = InteractableBlockType::Trampoline(height);
+                    interactable_blocks.push((line, ChunkPos::new(v[0], v[1], v[2]), b_type));
+                }
+                "portal" => {
+                    if args.len() != 5 {
+                        return Err(LevelError::Syntax {
+                            line,
+                            message: format!("expected 5 arguments, got {}", args.len()),
+                        });
+                    }
+                    let v = parse_args::<i32>(line, &args[..3], 3)?;
+                    let link = parse_args::<u8>(line, &args[3..4], 1)?[0];
+                    let facing = parse_facing(line, args[4])?;
+                    let b_type = InteractableBlockType::Portal { link, facing };
+                    interactable_blocks.push((line, ChunkPos::new(v[0], v[1], v[2]), b_type));
+                }
+                "enemy" => {
+                    if args.len() != 5 {
+                        return Err(LevelError::Syntax {
+                            line,
+                            message: format!("expected 5 arguments, got {}", args.len()),
+                        });
+                    }
+                    let e_type = parse_enemy_type(line, args[0])?;
+                    let e_size = parse_enemy_size(line, args[1])?;
+                    let v = parse_args::<f32>(line, &args[2..], 3)?;
+                    enemy_spawns.push((
+                        line,
+                        EnemySpawn { pos: Vec3::new(v[0], v[1], v[2]), size: e_size, e_type },
+                    ));
+                }
+                _ => {
+                    return Err(LevelError::Syntax {
+                        line,
+                        message: format!("unknown directive `{}`", directive),
+                    });
+                }
+            }
+        }
+
+        let size = size.ok_or(LevelError::Missing("size"))?;
+        let (player_line, player_start) = player_start.ok_or(LevelError::Missing("player"))?;
+        check_size(size)?;
+        let max = ChunkPos::from_parts(size, LocalPos { x: 0, y: 0, z: 0 });
+        let in_bounds = |pos: ChunkPos| {
+            pos.x >= 0 && pos.y >= 0 && pos.z >= 0 && pos.x < max.x && pos.y < max.y && pos.z < max.z
+        };
+        for (line, min, max, b_type) in fills {
+            for corner in [min, max] {
+                if !in_bounds(corner) {
+                    return Err(LevelError::OutOfBounds { line, pos: corner });
+                }
+            }
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        solid_blocks.push((line, ChunkPos::new(x, y, z), b_type));
+                    }
+                }
+            }
+        }
+        solid_blocks.sort_by_key(|&(line, _, _)| line); // back in file order, overlaps blame the later line
+
+        let mut occupied = HashSet::new();
+        for &(line, pos, _) in &solid_blocks {
+            if !in_bounds(pos) {
+                return Err(LevelError::OutOfBounds { line, pos });
+            }
+            if !occupied.insert(pos) {
+                return Err(LevelError::BlockOverlap { line, pos });
+            }
+        }
+        for &(line, pos, _) in &interactable_blocks {
+            if !in_bounds(pos) {
+                return Err(LevelError::OutOfBounds { line, pos });
+            }
+            if !occupied.insert(pos) {
+                return Err(LevelError::BlockOverlap { line, pos });
+            }
+        }
+        for player_tile in player_start_tiles(player_start) {
+            if !in_bounds(player_tile) {
+                return Err(LevelError::OutOfBounds { line: player_line, pos: player_tile });
+            }
+            if occupied.contains(&player_tile) {
+                return Err(LevelError::PlayerStartOverlap { line: player_line, pos: player_tile });
+            }
+        }
+        for (line, spawn) in &enemy_spawns {
+            for tile in enemy_spawn_tiles(spawn) {
+                if !in_bounds(tile) {
+                    return Err(LevelError::OutOfBounds { line: *line, pos: tile });
+                }
+                if occupied.contains(&tile) {
+                    return Err(LevelError::EnemySpawnOverlap { line: *line, pos: tile });
+                }
+            }
+        }
+
+        let interactable_blocks: Vec<(ChunkPos, InteractableBlockType)> = interactable_blocks
+            .into_iter()
+            .map(|(_, pos, b_type)| (pos, b_type))
+            .collect();
+        check_portal_links(&interactable_blocks)?;
+
+        Ok(Level {
+            meta,
+            size,
+            player_start,
+            solid_blocks: solid_blocks
+                .into_iter()
+                .map(|(_, pos, b_type)| (pos, b_type))
+                .collect(),
+            interactable_blocks,
+            enemy_spawns: enemy_spawns
+                .into_iter()
+                .map(|(_, spawn)| spawn)
+                .collect(),
+            rooms: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,14 @@
+//! the simulation of a run without a window, `main.rs` feeds it input from macroquad and draws it
+pub mod arena;
+pub mod floor;
+pub mod level;
+pub mod movement;
+pub mod replay;
+pub mod save;
+pub mod util;
+pub mod spawning;
+pub mod shooting;
+pub mod vox_level;
+pub mod world;
+
+pub use world::World;
@@ -1,31 +1,28 @@
-use std::{ collections::{ HashMap, VecDeque }, process::exit, time::Duration };
-use shared::{
-    config::{ window_conf, SCREEN_HEIGHT, SCREEN_WIDTH },
-    types::{ ChunkPos, CustomCamera3D, Enemies, EnemyHandle },
+use std::process::exit;
+use anabolic::{
+    replay::{ fingerprint, Replay, RunStart },
+    save::{ self, DEFAULT_SAVE_PATH },
+    spawning::SpawningSystem,
+    util::time_seed,
+    World,
 };
-use macroquad::prelude::*;
-use movement::MovementSystem;
 use shared::{
-    config::{
-        CHUNK_SIZE,
-        INITIAL_PLAYER_POS,
-        JUMP_STRENGTH,
-        LOOK_SPEED,
-        PHYSICS_FRAME_TIME,
-        WORLD_UP,
-    },
-    types::{ ChunkVec3, EntityType, Player, SolidBlocks, Textures, WorldEvent, VoxelMesh },
-    Lazy,
+    config::{ window_conf, LOOK_SPEED, MAX_FRAME_TIME, PHYSICS_FRAME_TIME, WORLD_UP },
+    types::{ CustomCamera3D, Player, TickInput, VoxelMesh },
 };
-use shooting::shoot;
-use spawning::{ update_spawning_system, SpawningSystem };
-use util::{ load_voxel_data, vec3_no_y };
-pub mod movement;
-pub mod util;
-pub mod spawning;
-pub mod shooting;
+use macroquad::prelude::*;
 
-use render::{ Drawer, Screen };
+use render::Drawer;
+#[cfg(feature = "hot-reload")]
+use {
+    anabolic::{ floor::RoomState, util::load_voxel_data },
+    render::Screen,
+    shared::{ types::Textures, Lazy },
+    std::collections::HashMap,
+};
+use input::{ Action, Bindings, DEFAULT_BINDINGS_PATH };
+mod input;
+#[cfg(feature = "hot-reload")]
 static TEXTURE_TO_VOXEL_MESH: Lazy<HashMap<Textures, VoxelMesh>> = Lazy::new(|| {
     let mut map = HashMap::new();
     map.insert(Textures::Pistol, load_voxel_data("textures/pistol.vox"));
@@ -38,10 +35,8 @@ mod hot_r_renderer {
     hot_functions_from_file!("renderer/src/debug.rs");
     use render::Screen;
     use shared::{
-        config::CHUNK_SIZE,
         types::{
             ChunkVec3,
-            EntityType,
             AnimationState,
             PossibleEnemySizes,
             AnimationCallbackEvent,
@@ -49,263 +44,161 @@ mod hot_r_renderer {
             Enemies,
             VoxelMesh,
             EnemyType,
+            WorldLayout,
+            SolidBlocks,
+            BlockRegistry,
+            InteractableBlocks,
+            LayoutIssue,
         },
         Vec3,
     };
 }
-pub struct World {
-    player: Player,
+
+/// what belongs to the window and not to the run. Input gets collected here every frame
+/// and handed to the world once per physics step, the camera follows the player from here
+struct Client {
+    bindings: Bindings,
     camera: Camera3D,
-    enemies: Enemies,
-    solid_blocks: SolidBlocks,
-    pub world_layout: [
-        [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-        CHUNK_SIZE as usize
-    ],
     grabbed: bool,
-    world_event_queue: VecDeque<WorldEvent>,
+    show_layout_issues: bool,
+    input: TickInput,
 }
 
-impl World {
-    fn default() -> Self {
-        let world_layout: [
-            [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-            CHUNK_SIZE as usize
-        ] = core::array::from_fn(|_| {
-            core::array::from_fn(|_| { core::array::from_fn(|_| { Vec::new() }) })
-        });
-        let mut world = World {
-            player: Player::default(),
-            camera: Camera3D {
-                position: INITIAL_PLAYER_POS,
-                up: WORLD_UP,
-                target: vec3(1.0, 1.8, 0.0),
-                ..Default::default()
-            },
-            grabbed: true,
-            world_layout: world_layout,
-            enemies: Enemies::new(),
-            solid_blocks: SolidBlocks::new(), // make static
-            world_event_queue: VecDeque::new(),
-        };
-        world.world_layout[INITIAL_PLAYER_POS.x as usize][INITIAL_PLAYER_POS.y as usize][
-            INITIAL_PLAYER_POS.z as usize
-        ].push(EntityType::Player);
-
-        for x in 0..CHUNK_SIZE as usize {
-            for z in 0..CHUNK_SIZE as usize {
-                world.world_layout[x][0][z].push(EntityType::SolidBlock);
-                world.solid_blocks.new_block(ChunkVec3(vec3(x as f32, 0.0, z as f32)));
-            }
-        }
-        world.world_layout[3][8][3].push(
-            EntityType::Enemy(
-                world.enemies.new_enemy(
-                    ChunkVec3(vec3(3.0, 8.0, 3.0)),
-                    vec3(1.0, 0.0, 0.0),
-                    shared::types::PossibleEnemySizes::SMALL,
-                    1,
-                    shared::types::EnemyType::Regular
-                )
-            )
-        );
-        world.world_layout[12][8][3].push(
-            EntityType::Enemy(
-                world.enemies.new_enemy(
-                    ChunkVec3(vec3(12.0, 8.0, 3.0)),
-                    vec3(-1.0, 0.0, 0.0),
-                    shared::types::PossibleEnemySizes::SMALL,
-                    1,
-                    shared::types::EnemyType::Regular
-                )
-            )
-        );
-        world
+impl Client {
+    /// alpha is how far the render frame is between the last two physics steps, so the camera
+    /// moves smoothly even when the frame rate is not a multiple of the physics rate.
+    /// `look` is mouse movement collected for the next step, the view turns right away
+    fn update_camera(&mut self, player: &Player, alpha: f32, look: Vec2) {
+        let front = player.look_dir_after(look);
+        let right = front.cross(WORLD_UP).normalize();
+        self.camera.position = player.eye_pos_at(alpha).0;
+        self.camera.up = right.cross(front).normalize();
+        self.camera.target = self.camera.position + front;
     }
 
-    fn remove_enemy(&mut self, h: EnemyHandle) {
-        let index = h.0 as usize;
-        if index < self.enemies.positions.len() {
-            let position = self.enemies.positions[index];
-            let size = self.enemies.size[index];
-            let hitbox = Enemies::get_hitbox_from_size(size);
-
-            let occupied_tiles = Enemies::get_occupied_tiles(&position, &(hitbox * 0.5));
-            println!("occupied {:?}", occupied_tiles);
-            for tile in occupied_tiles {
-                self.world_layout[tile.x as usize][tile.y as usize][tile.z as usize].retain(
-                    |entity| {
-                        match entity {
-                            EntityType::Enemy(h) => { h != h }
-                            _ => { true }
-                        }
-                    }
-                );
-            }
-            for (x, x_layer) in self.world_layout.iter().enumerate() {
-                for (y, y_layer) in x_layer.iter().enumerate() {
-                    for (z, entities) in y_layer.iter().enumerate() {
-                        for entity in entities {
-                            if let EntityType::Enemy(eh) = entity {
-                                if *eh == h {
-                                    println!(
-                                        "Enemy found at position: x: {}, y: {}, z: {}",
-                                        x,
-                                        y,
-                                        z
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            self.enemies.remove_enemy(h);
-        }
-    }
-
-    fn handle_world_events(&mut self) {
-        while let Some(event) = self.world_event_queue.pop_front() {
-            println!("event {:?}", event);
-            match event {
-                WorldEvent::KillEnemy(h) => {
-                    self.remove_enemy(h);
-                }
-                WorldEvent::HitEnemy(h) => {
-                    let index = h.0 as usize;
-                    if index < self.enemies.healths.len() {
-                        if self.enemies.healths[index] > 1 {
-                            self.enemies.healths[index] -= 1;
-                        } else {
-                            self.world_event_queue.push_back(WorldEvent::KillEnemy(h));
-                        }
-                    }
-                }
-            }
-        }
-    }
-    fn update(&mut self, spawner: &mut SpawningSystem) {
-        self.handle_world_events();
-        let player_chunk = self.player.pos.to_chunk();
-        MovementSystem::update_player(
-            &mut self.player.pos,
-            &mut self.player.vel,
-            &self.enemies,
-            &mut self.world_layout
-        );
-        MovementSystem::update_enemies(&self.player.pos, &mut self.enemies, &mut self.world_layout);
-        update_spawning_system(self, spawner, Duration::from_secs_f32(PHYSICS_FRAME_TIME));
-        debug_assert!(
-            self.world_layout[player_chunk.x as usize][player_chunk.y as usize][
-                player_chunk.z as usize
-            ].contains(&EntityType::Player)
-        );
-    }
-
-    fn handle_input(&mut self) {
-        if is_key_pressed(KeyCode::Escape) {
+    fn handle_input(&mut self, world: &mut World) {
+        if self.bindings.pressed(Action::ToggleCursor) {
             self.grabbed = !self.grabbed;
             set_cursor_grab(self.grabbed);
             show_mouse(!self.grabbed);
         }
-        if is_mouse_button_pressed(MouseButton::Left) {
+        if self.bindings.pressed(Action::Shoot) {
             self.grabbed = true;
             set_cursor_grab(self.grabbed);
             show_mouse(!self.grabbed);
-            self.world_event_queue.extend(
-                shoot(&mut self.player, &self.enemies, &self.world_layout)
-            );
+            self.input.shoot = true;
+        }
+        if self.bindings.pressed(Action::NextWeapon) {
+            self.input.next_weapon = true;
         }
-        if is_key_pressed(KeyCode::E) {
-            self.player.swap_next_weapon();
+        if self.bindings.pressed(Action::LayoutOverlay) {
+            self.show_layout_issues = !self.show_layout_issues;
         }
 
-        let delta = get_frame_time();
         if self.grabbed {
+            // the mouse moved this far since the last frame, so no scaling by the frame time
             let mouse_delta = mouse_delta_position();
-
-            self.player.yaw -= mouse_delta.x * delta * LOOK_SPEED;
-            self.player.pitch -= mouse_delta.y * delta * -LOOK_SPEED;
-            self.player.pitch = self.player.pitch.clamp(-1.5, 1.5);
-            let front = vec3(
-                self.player.yaw.cos() * self.player.pitch.cos(),
-                self.player.pitch.sin(),
-                self.player.yaw.sin() * self.player.pitch.cos()
-            ).normalize();
-
-            let right = front.cross(WORLD_UP).normalize();
-            let up = right.cross(front).normalize();
-            let mut player_vel = Vec3::ZERO;
-            if is_key_down(KeyCode::Up) || is_key_down(KeyCode::W) {
-                player_vel = vec3_no_y(front);
-            }
-            if is_key_down(KeyCode::Down) || is_key_down(KeyCode::S) {
-                player_vel = -vec3_no_y(front);
+            self.input.look += vec2(-mouse_delta.x, mouse_delta.y) * LOOK_SPEED;
+            // every held key adds up, so diagonals work and opposite keys cancel out
+            let mut movement = Vec2::ZERO;
+            if self.bindings.down(Action::MoveForward) {
+                movement.y += 1.0;
             }
-            if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
-                player_vel = -vec3_no_y(right);
+            if self.bindings.down(Action::MoveBack) {
+                movement.y -= 1.0;
             }
-            if is_key_down(KeyCode::Right) || is_key_down(KeyCode::D) {
-                player_vel = vec3_no_y(right);
+            if self.bindings.down(Action::MoveLeft) {
+                movement.x -= 1.0;
             }
-            if is_key_down(KeyCode::Space) {
-                player_vel.y = JUMP_STRENGTH;
+            if self.bindings.down(Action::MoveRight) {
+                movement.x += 1.0;
             }
-            self.player.vel = player_vel;
-            self.camera.position = self.player.pos.0;
-            self.camera.up = up;
-            self.camera.target = self.camera.position + front;
-        }
-        if is_key_down(KeyCode::V) {
-            exit(0);
-        }
-        if is_key_down(KeyCode::R) {
-            self.world_layout = World::default().world_layout;
-            self.enemies = World::default().enemies;
+            self.input.movement = movement;
+            self.input.jump_pressed |= self.bindings.pressed(Action::Jump);
+            self.input.jump_held = self.bindings.down(Action::Jump);
+            self.input.dash |= self.bindings.pressed(Action::Dash);
+            self.input.sprint = self.bindings.down(Action::Sprint);
+            self.input.crouch = self.bindings.down(Action::Crouch);
+        } else {
+            // nothing counts as held while the cursor is free
+            self.input = TickInput {
+                shoot: self.input.shoot,
+                next_weapon: self.input.next_weapon,
+                ..Default::default()
+            };
         }
-
-        if is_key_down(KeyCode::F) {
-            println!(
+        if self.bindings.pressed(Action::PrintPositions) {
+            // debug output goes to stderr, stdout is left to the replay and verify messages
+            eprintln!(
                 "player pos as chunk {:?}, non chunk {:?}",
-                self.player.pos.to_chunk(),
-                self.player.pos
+                world.player.body.pos.to_chunk(),
+                world.player.body.pos
             );
-            println!(
+            eprintln!(
                 "Enemies {:?}",
-                self.enemies.positions.iter().map(|f| f.to_chunk())
+                world.enemies.bodies.iter().map(|body| body.pos.to_chunk()).collect::<Vec<_>>()
             );
         }
     }
 
     #[cfg(not(feature = "hot-reload"))]
-    fn draw(&self) {
+    fn draw(&self, _world: &World, _alpha: f32) {
         set_camera(&self.camera);
-        // draw_cube_wires(self.player.pos.0, Vec3::new(1.0, 2.0, 1.0), RED);
+        // draw_cube_wires(self.player.body.pos.0, Vec3::new(1.0, 2.0, 1.0), RED);
         // hot_r_renderer::render_world(&self.world_layout);
         // hot_r_renderer::render_default_enemy(vec3(5.0, 1.0, 5.0), Vec3::splat(1.0));
         set_default_camera()
     }
 
     #[cfg(feature = "hot-reload")]
-    fn draw(&mut self, screen: &Screen) {
-        // needs to be mutable because of animation states, maybe refactor into a world separate struct?
+    fn draw(&self, world: &mut World, screen: &Screen, alpha: f32) {
+        // the world needs to be mutable because of animation states
 
-        use shared::config::SCREEN_WIDTH;
+        use shared::config::{ SCREEN_HEIGHT, SCREEN_WIDTH };
         set_camera(&self.camera);
-        hot_r_renderer::update_animations(&mut self.enemies.animation_state, get_frame_time());
-        hot_r_renderer::update_animation(&mut self.player.animation_state, get_frame_time());
-        hot_r_renderer::render_solid_blocks(screen, &self.solid_blocks.positions);
-        hot_r_renderer::render_regular_enemies(screen, &self.enemies);
+        hot_r_renderer::update_animations(&mut world.enemies.animation_state, get_frame_time());
+        hot_r_renderer::update_animation(&mut world.player.animation_state, get_frame_time());
+        hot_r_renderer::render_solid_blocks(screen, &world.solid_blocks, &world.block_registry);
+        hot_r_renderer::render_interactable_blocks(screen, &world.interactable_blocks);
+        hot_r_renderer::render_regular_enemies(screen, &world.enemies, alpha);
 
-        hot_r_renderer::render_enemy_world_positions(screen, &self.world_layout, &self.enemies);
+        hot_r_renderer::render_enemy_world_positions(screen, &world.world_layout, &world.enemies);
+        // checking is slow, so only while the overlay is open
+        let layout_issues = if self.show_layout_issues { world.check_consistency() } else { Vec::new() };
+        hot_r_renderer::render_layout_issues(screen, &layout_issues);
         set_default_camera();
+        if self.show_layout_issues {
+            draw_text(format!("Layout issues: {}", layout_issues.len()).as_str(), 10.0, 90.0, 20.0, WHITE);
+            for (i, issue) in layout_issues.iter().take(10).enumerate() {
+                draw_text(format!("{:?}", issue).as_str(), 10.0, 110.0 + (i as f32) * 20.0, 20.0, RED);
+            }
+        }
         draw_text(
-            &format!("Enemies: {}", self.enemies.e_type.len()),
+            format!("Enemies: {}", world.enemies.e_type.len()).as_str(),
             (SCREEN_WIDTH as f32) - 150.0, // X position (top left corner)
             20.0, // Y position (top left corner)
             30.0, // Font size
             WHITE // Color
         );
+        draw_text(&world.level.meta.name, 10.0, 30.0, 20.0, WHITE);
+        if let Some(room) = world.rooms.iter().position(|room| room.contains(world.player.body.pos)) {
+            let status = match world.rooms[room].state {
+                RoomState::Locked => "locked",
+                _ => "open",
+            };
+            draw_text(
+                format!("Room {}/{} ({})", room + 1, world.rooms.len(), status).as_str(),
+                10.0,
+                70.0,
+                20.0,
+                WHITE
+            );
+        }
+        draw_text(format!("Health: {:.0}", world.player.health).as_str(), 10.0, 50.0, 20.0, WHITE);
+        // dash cooldown, the bar fills back up until the next dash is ready
+        let dash_ready = 1.0 - world.player.dash.cooldown_fraction();
+        draw_rectangle(130.0, 38.0, 100.0 * dash_ready, 14.0, if dash_ready >= 1.0 { SKYBLUE } else { GRAY });
+        draw_rectangle_lines(130.0, 38.0, 100.0, 14.0, 1.0, WHITE);
         let weapon_mesh = TEXTURE_TO_VOXEL_MESH.get(&Textures::Pistol).expect(
             "Failed to load weapon"
         );
@@ -313,9 +206,23 @@ impl World {
         hot_r_renderer::render_player_pov(
             screen,
             weapon_mesh,
-            self.player.get_current_weapon().w_type,
-            &self.player.animation_state
+            world.player.get_current_weapon().w_type,
+            &world.player.animation_state
         );
+        if world.is_game_over() {
+            // the seed is all it takes to play the same run again, see `--seed`
+            let center = vec2(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32) * 0.5;
+            draw_rectangle(
+                0.0,
+                0.0,
+                SCREEN_WIDTH as f32,
+                SCREEN_HEIGHT as f32,
+                Color::new(0.0, 0.0, 0.0, 0.6)
+            );
+            draw_text("GAME OVER", center.x - 150.0, center.y - 40.0, 60.0, RED);
+            draw_text(format!("Seed: {}", world.rng.seed).as_str(), center.x - 150.0, center.y, 30.0, WHITE);
+            draw_text("Jump to start a new run", center.x - 150.0, center.y + 40.0, 30.0, WHITE);
+        }
     }
 }
 
@@ -371,32 +278,219 @@ impl Drawer for DrawerImpl {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let mut elapsed_time = 0.0;
-    let mut world = World::default();
-    let mut spawning_sys = SpawningSystem::new();
+/// where the input of every physics step comes from
+enum Session {
+    Play,
+    Record(String, Replay), // the replay gets written to this path once the recording stops
+    Watch(Replay, usize), // next recorded step
+}
+
+impl Session {
+    fn step(&mut self, world: &mut World, spawner: &mut SpawningSystem, live: &TickInput) {
+        let finished = match self {
+            Session::Play => {
+                world.update(spawner, live);
+                false
+            }
+            Session::Record(_, replay) => {
+                world.update(spawner, live);
+                replay.inputs.push(*live);
+                false
+            }
+            Session::Watch(replay, next) => {
+                if let Some(input) = replay.inputs.get(*next) {
+                    world.update(spawner, input);
+                    *next += 1;
+                }
+                *next >= replay.inputs.len()
+            }
+        };
+        if finished {
+            self.stop(world, spawner);
+        }
+    }
+
+    /// writes recordings and reports how replays ended. Anything that changes the run outside of
+    /// `World::update` has to stop the session first, a replay could not follow it
+    fn stop(&mut self, world: &World, spawner: &SpawningSystem) {
+        match std::mem::replace(self, Session::Play) {
+            Session::Play => {}
+            Session::Record(path, mut replay) => {
+                replay.fingerprint = fingerprint(world, spawner);
+                match replay.save_to_file(&path) {
+                    Ok(()) => println!("Recorded {} steps to {}", replay.inputs.len(), path),
+                    Err(err) => eprintln!("Failed to write replay {}: {}", path, err),
+                }
+            }
+            Session::Watch(replay, next) => {
+                if next < replay.inputs.len() {
+                    println!("Replay stopped after {} of {} steps", next, replay.inputs.len());
+                } else if fingerprint(world, spawner) == replay.fingerprint {
+                    println!("Replay finished the same as the recording");
+                } else {
+                    println!("Replay finished different from the recording");
+                }
+            }
+        }
+    }
+}
+
+fn load_replay(path: &str) -> Replay {
+    Replay::load_from_file(path).unwrap_or_else(|err| {
+        eprintln!("Failed to load replay {}: {}", path, err);
+        exit(1);
+    })
+}
+
+fn level_start(path: &str) -> RunStart {
+    RunStart::level(path).unwrap_or_else(|err| {
+        eprintln!("Failed to load level: {}", err);
+        exit(1);
+    })
+}
+
+fn parse_seed(seed: &str) -> u64 {
+    seed.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid seed {}", seed);
+        exit(1);
+    })
+}
+
+/// `anabolic` starts a run on a freshly generated floor, `anabolic --seed <n>` replays a specific run,
+/// the one from the game over screen for example, in front of a level path it seeds that arena instead.
+/// `anabolic --continue` resumes the saved run and `anabolic <path>` plays a .lvl or .vox arena.
+/// `--record <file>` in front of a new run writes its input to a replay, `anabolic --replay <file>` plays
+/// one back and `anabolic --verify <file>` checks it without a window, failing if the run turns out different
+fn run_from_args() -> (World, SpawningSystem, Session) {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (record, args) = match args.as_slice() {
+        [flag, path, rest @ ..] if flag == "--record" => (Some(path.clone()), rest),
+        rest => (None, rest),
+    };
+    let replay = match args {
+        [] => Replay::new(RunStart::Floor, time_seed()),
+        [flag] if flag == "--continue" && record.is_none() => {
+            let (world, spawner) = save::load_from_file(DEFAULT_SAVE_PATH).unwrap_or_else(|err| {
+                eprintln!("Failed to continue from {}: {}", DEFAULT_SAVE_PATH, err);
+                exit(1);
+            });
+            return (world, spawner, Session::Play);
+        }
+        [flag, seed] if flag == "--seed" => Replay::new(RunStart::Floor, parse_seed(seed)),
+        [flag, seed, level_path] if flag == "--seed" && !level_path.starts_with("--") =>
+            Replay::new(level_start(level_path), parse_seed(seed)),
+        [flag, path] if flag == "--replay" && record.is_none() => {
+            let replay = load_replay(path);
+            let (world, spawner) = replay.start_run().unwrap_or_else(|err| {
+                eprintln!("Failed to start replay {}: {}", path, err);
+                exit(1);
+            });
+            return (world, spawner, Session::Watch(replay, 0));
+        }
+        [flag, path] if flag == "--verify" && record.is_none() => {
+            match load_replay(path).verify() {
+                Ok(true) => {
+                    println!("{} plays out the same as recorded", path);
+                    exit(0);
+                }
+                Ok(false) => eprintln!("{} plays out different from the recording", path),
+                Err(err) => eprintln!("Failed to start replay {}: {}", path, err),
+            }
+            exit(1);
+        }
+        [level_path] if !level_path.starts_with("--") => Replay::new(level_start(level_path), time_seed()),
+        _ => {
+            eprintln!(
+                "usage: anabolic [--record <replay>] [--seed <n>] [<level path>] | --continue | --replay <replay> | --verify <replay>"
+            );
+            exit(1);
+        }
+    };
+    let (world, spawner) = replay.start_run().unwrap_or_else(|err| {
+        eprintln!("Failed to load level: {}", err);
+        exit(1);
+    });
+    let session = match record {
+        Some(path) => Session::Record(path, replay),
+        None => Session::Play,
+    };
+    (world, spawner, session)
+}
+
+fn main() {
+    // arguments first, so verifying a replay never opens a window
+    let (world, spawning_sys, session) = run_from_args();
+    macroquad::Window::from_config(window_conf(), run(world, spawning_sys, session));
+}
+
+async fn run(mut world: World, mut spawning_sys: SpawningSystem, mut session: Session) {
+    let mut accumulator = 0.0;
     #[cfg(feature = "hot-reload")]
     let drawer: Box<dyn Drawer> = Box::new(DrawerImpl {});
     #[cfg(feature = "hot-reload")]
     let screen = Screen { drawer };
-    set_cursor_grab(true);
-    show_mouse(false);
+    let bindings = Bindings::load(DEFAULT_BINDINGS_PATH).unwrap_or_else(|err| {
+        eprintln!("Failed to load key bindings from {}, using the defaults: {}", DEFAULT_BINDINGS_PATH, err);
+        Bindings::default()
+    });
+    let mut client = Client {
+        bindings,
+        camera: Camera3D { up: WORLD_UP, ..Default::default() },
+        grabbed: true,
+        show_layout_issues: false,
+        input: TickInput::default(),
+    };
+    set_cursor_grab(client.grabbed);
+    show_mouse(!client.grabbed);
+    prevent_quit(); // recordings get written before the window closes
 
     loop {
         clear_background(BLACK);
-        elapsed_time += get_frame_time();
-        world.handle_input();
-        while elapsed_time >= PHYSICS_FRAME_TIME {
-            world.update(&mut spawning_sys);
-            elapsed_time = 0.0;
+        accumulator = (accumulator + get_frame_time()).min(MAX_FRAME_TIME);
+        client.handle_input(&mut world);
+        // saving needs the spawner as well, so it is handled here and not in handle_input
+        let save_and_quit = client.bindings.pressed(Action::SaveAndQuit);
+        if client.bindings.pressed(Action::QuickSave) || save_and_quit {
+            if let Err(err) = save::save_to_file(DEFAULT_SAVE_PATH, &world, &spawning_sys) {
+                eprintln!("Failed to save run: {}", err);
+            }
+        }
+        if save_and_quit || is_quit_requested() {
+            session.stop(&world, &spawning_sys);
+            break;
+        }
+        if client.bindings.pressed(Action::ReloadLevel) {
+            session.stop(&world, &spawning_sys);
+            let level = world.level.clone();
+            world.load_level(&level);
+            spawning_sys = SpawningSystem::new();
+        }
+        if client.bindings.pressed(Action::QuickLoad) {
+            session.stop(&world, &spawning_sys);
+            match save::load_from_file(DEFAULT_SAVE_PATH) {
+                Ok((loaded, spawner)) => {
+                    world = loaded;
+                    spawning_sys = spawner;
+                }
+                Err(err) => eprintln!("Failed to load run: {}", err),
+            }
+        }
+        // as many steps as the frame took, the remainder carries over to the next frame
+        while accumulator >= PHYSICS_FRAME_TIME {
+            session.step(&mut world, &mut spawning_sys, &client.input);
+            client.input.end_tick();
+            accumulator -= PHYSICS_FRAME_TIME;
         }
+        let alpha = accumulator / PHYSICS_FRAME_TIME;
+        // while watching a replay the live mouse movement goes nowhere
+        let look = if matches!(session, Session::Watch(..)) { Vec2::ZERO } else { client.input.look };
+        client.update_camera(&world.player, alpha, look);
         #[cfg(feature = "hot-reload")]
-        world.draw(&screen);
+        client.draw(&mut world, &screen, alpha);
         #[cfg(not(feature = "hot-reload"))]
-        world.draw();
+        client.draw(&world, alpha);
 
-        draw_text(&format!("FPS: {}", get_fps()), 10.0, 10.0, 20.0, WHITE);
+        draw_text(format!("FPS: {}", get_fps()).as_str(), 10.0, 10.0, 20.0, WHITE);
         next_frame().await;
     }
 }
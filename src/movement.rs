@@ -1,319 +1,571 @@
+use std::f32::consts::PI;
+
 use shared::{
-    config::{ CHUNK_SIZE, GRAVITY, MOVE_SPEED, PHYSICS_FRAME_TIME, WORLD_BORDER },
-    types::{ ChunkPos, ChunkVec3, Enemies, EnemyHandle, EnemyType, EntityType, PossibleEnemySizes },
+    config::{
+        AIR_CONTROL,
+        COYOTE_TIME,
+        CROUCH_SPEED,
+        DASH_COOLDOWN,
+        DASH_INVULNERABILITY,
+        DASH_SPEED,
+        DASH_TIME,
+        ENEMY_AVOID_DISTANCE,
+        ENEMY_DEFAULT_MOVE_SPEED,
+        ENEMY_SEPARATION,
+        ENEMY_SEPARATION_STRENGTH,
+        ENEMY_STEERING,
+        GRAVITY,
+        GROUND_ACCELERATION,
+        GROUND_FRICTION,
+        JUMP_CUT,
+        JUMP_STRENGTH,
+        PHYSICS_FRAME_TIME,
+        PLAYER_CROUCH_HALF_HEIGHT,
+        PLAYER_HALF_HITBOX,
+        SPRINT_SPEED,
+        STEP_HEIGHT,
+        WALK_SPEED,
+    },
+    types::{
+        BlockRegistry,
+        BlockTypeId,
+        Body,
+        ChunkPos,
+        ChunkVec3,
+        Enemies,
+        EnemyHandle,
+        EnemyType,
+        EntityType,
+        InteractableBlocks,
+        JumpState,
+        Player,
+        WorldLayout,
+    },
     vec3,
     Vec3,
 };
 
+use crate::util::vec3_no_y;
+
 pub struct MovementSystem;
 
+/// everything a moving body can bump into besides the world itself
+struct Colliders<'a> {
+    entity: EntityType, // the body being moved, it doesnt collide with itself
+    enemies: &'a Enemies,
+    player: &'a Body,
+    world_layout: &'a WorldLayout,
+}
+
+impl Colliders<'_> {
+    fn body_of(&self, entity: EntityType) -> Option<&Body> {
+        if entity == self.entity {
+            return None;
+        }
+        match entity {
+            EntityType::Player => Some(self.player),
+            EntityType::Enemy(h) => {
+                let index = h.0 as usize;
+                match self.enemies.e_type.get(index) {
+                    Some(EnemyType::Empty) | None => None,
+                    Some(_) => self.enemies.bodies.get(index),
+                }
+            }
+            EntityType::InteractableBlock(_) => None,
+        }
+    }
+}
+
+const PORTAL_REACH: f32 = 0.1; // how close an entity has to get to a portal to walk through it
+const SKIN: f32 = 0.001; // gap a body keeps to whatever stopped it, so touching faces dont count as overlapping
+const RAMP_SNAP: f32 = 0.5; // how far below the feet a ramp still counts as walked on
+
 impl MovementSystem {
+    /// returns the block the player is standing on
     pub fn update_player(
-        pos: &mut ChunkVec3,
-        vel: &mut Vec3,
+        player: &mut Player,
         enemies: &Enemies,
-        chunk: &mut [
-            [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-            CHUNK_SIZE as usize
-        ]
-    ) {
-        vel.y += GRAVITY * PHYSICS_FRAME_TIME;
-
-        let mut new_pos = ChunkVec3(
-            pos.0 + Vec3::new(0.0, vel.y, 0.0) * PHYSICS_FRAME_TIME * MOVE_SPEED
-        );
-        if Self::check_collision_player(&(new_pos + vec3(0.0, -1.0, 0.0)), enemies, chunk) {
-            vel.y = 0.0;
-            new_pos = *pos;
-        }
-
-        new_pos = ChunkVec3(
-            new_pos.0 + Vec3::new(vel.x, 0.0, 0.0) * PHYSICS_FRAME_TIME * MOVE_SPEED
-        );
-        if Self::check_collision_player(&new_pos, enemies, chunk) {
-            vel.x = 0.0;
-            new_pos = ChunkVec3(pos.0 + Vec3::new(0.0, new_pos.0.y - pos.0.y, 0.0));
-        }
-
-        new_pos = ChunkVec3(
-            new_pos.0 + Vec3::new(0.0, 0.0, vel.z) * PHYSICS_FRAME_TIME * MOVE_SPEED
-        );
-        if Self::check_collision_player(&new_pos, enemies, chunk) {
-            vel.z = 0.0;
-            new_pos = ChunkVec3(
-                pos.0 + Vec3::new(new_pos.0.x - pos.0.x, new_pos.0.y - pos.0.y, 0.0)
-            );
-        }
-        new_pos.0 = new_pos.0.clamp(Vec3::splat(1.0), Vec3::splat((CHUNK_SIZE as f32) - 1.0));
-        Self::update_world_position(chunk, EntityType::Player, &new_pos, &pos, &Vec3::splat(0.5));
-        *pos = new_pos;
+        interactable_blocks: &InteractableBlocks,
+        world_layout: &mut WorldLayout,
+        block_registry: &BlockRegistry
+    ) -> Option<BlockTypeId> {
+        player.body.prev_pos = player.body.pos;
+        let prev = player.body;
+        Self::update_crouch(player, world_layout);
+        let friction = if player.body.grounded {
+            let (ground_block, _) = Self::ground(&player.body, world_layout);
+            ground_block.map_or(1.0, |b_type| block_registry.get(b_type).friction)
+        } else {
+            1.0
+        };
+        Self::update_dash(player);
+        if !player.dash.is_dashing() {
+            Self::accelerate(player, friction);
+        }
+        Self::update_jump(&mut player.body, &mut player.jump);
+        if let Some(rotation) = Self::teleport(&mut player.body, interactable_blocks, world_layout) {
+            player.yaw += rotation;
+            player.input.wish_dir = Vec3::ZERO; // the keys still point where the player came from
+        }
+
+        let mut body = player.body;
+        let colliders = Colliders { entity: EntityType::Player, enemies, player: &player.body, world_layout };
+        let ground_block = Self::integrate(&mut body, &colliders, interactable_blocks, block_registry);
+        player.body = body;
+        if body.grounded {
+            player.jump.rising = false;
+        }
+        Self::update_world_position(world_layout, EntityType::Player, &prev, &body);
+        ground_block
     }
 
     pub fn update_enemies(
-        player_pos: &ChunkVec3,
+        player: &Body,
         enemies: &mut Enemies,
-        chunk: &mut [
-            [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-            CHUNK_SIZE as usize
-        ]
+        interactable_blocks: &InteractableBlocks,
+        world_layout: &mut WorldLayout,
+        block_registry: &BlockRegistry
     ) {
-        for i in 0..enemies.positions.len() {
-            let (left, right) = enemies.positions.split_at_mut(i);
-            let (current, right) = right.split_at_mut(1);
-            let pos = &mut current[0];
-            let initial_pos = pos.clone();
-            let other_positions: Vec<ChunkVec3> = left
-                .iter()
-                .chain([initial_pos].iter()) // to upkeep valid handles
-                .chain(right.iter())
-                .cloned()
-                .collect();
-
-            let enemy_handle = EnemyHandle(i as u16);
-            let half_hitbox = Enemies::get_hitbox_from_size(enemies.size[i]) * 0.5;
-            let vel = &mut enemies.velocities[i];
+        for i in 0..enemies.bodies.len() {
             if enemies.e_type[i] == EnemyType::Empty {
                 continue;
             }
+            let entity = EntityType::Enemy(EnemyHandle(i as u16));
+            enemies.bodies[i].prev_pos = enemies.bodies[i].pos;
+            let prev = enemies.bodies[i];
+            let mut body = prev;
+            let colliders = Colliders { entity, enemies, player, world_layout };
+            Self::steer_enemy(&mut body, &colliders);
+            Self::teleport(&mut body, interactable_blocks, world_layout);
+            Self::integrate(&mut body, &colliders, interactable_blocks, block_registry);
+            enemies.bodies[i] = body;
+            Self::update_world_position(world_layout, entity, &prev, &body);
+        }
+    }
+
+    /// gravity, then one sweep per axis against the world and every other body. Bodies on the ground walk up
+    /// ledges and ramps, landing on a trampoline launches them. Returns the block the body stands on
+    fn integrate(
+        body: &mut Body,
+        colliders: &Colliders,
+        interactable_blocks: &InteractableBlocks,
+        block_registry: &BlockRegistry
+    ) -> Option<BlockTypeId> {
+        let world_layout = colliders.world_layout;
+        let half = body.half_extents;
+        body.vel.y += GRAVITY * PHYSICS_FRAME_TIME;
+        let delta = body.vel * PHYSICS_FRAME_TIME;
 
-            vel.x = (player_pos.0.x - pos.0.x) * 0.3; // make farther enemies quicker, but dont overdo it
-            vel.z = (player_pos.0.z - pos.0.z) * 0.3;
-
-            const MAX_XYZ: Vec3 = Vec3::splat((CHUNK_SIZE as f32) - WORLD_BORDER); // small enough to not get rounded to chunk size
-            let x_border = pos.0.x + half_hitbox.x * vel.x.signum();
-            let curr_pos = ChunkVec3(
-                Vec3::new(x_border + vel.x * PHYSICS_FRAME_TIME, pos.0.y, pos.0.z)
-            );
-            if
-                curr_pos.0.x < MAX_XYZ.x &&
-                Self::enemy_check_if_chunk_is_valid_pos(
-                    curr_pos,
-                    enemy_handle,
-                    &half_hitbox,
-                    &other_positions,
-                    &enemies.size,
-                    &enemies.e_type,
-                    chunk
-                )
-            {
-                pos.0.x = curr_pos.0.x - half_hitbox.x * vel.x.signum();
+        let mut ground_block = None;
+        let was_grounded = body.grounded;
+        body.grounded = false;
+        if Self::sweep(&mut body.pos.0, half, 1, delta.y, colliders) {
+            let landed = body.vel.y < 0.0;
+            body.vel.y = 0.0;
+            if landed {
+                body.grounded = true;
+                let below;
+                (ground_block, below) = Self::ground(body, world_layout);
+                if let Some(height) = interactable_blocks.trampoline_in(&below) {
+                    body.vel.y = Self::launch_speed(height);
+                }
             }
-            let y_border = pos.0.y + half_hitbox.y * vel.y.signum();
-            let curr_pos = ChunkVec3(
-                Vec3::new(pos.0.x, y_border + vel.y * PHYSICS_FRAME_TIME, pos.0.z)
-            );
-            if
-                curr_pos.0.y < MAX_XYZ.y &&
-                Self::enemy_check_if_chunk_is_valid_pos(
-                    curr_pos,
-                    enemy_handle,
-                    &half_hitbox,
-                    &other_positions,
-                    &enemies.size,
-                    &enemies.e_type,
-                    chunk
-                )
-            {
-                pos.0.y = curr_pos.0.y - half_hitbox.y * vel.y.signum();
-                vel.y += GRAVITY * PHYSICS_FRAME_TIME;
-            } 
-            let z_border = pos.0.z + half_hitbox.z * vel.z.signum();
-            let curr_pos = ChunkVec3(
-                Vec3::new(pos.0.x, pos.0.y, z_border + vel.z * PHYSICS_FRAME_TIME)
-            );
-            if
-                curr_pos.0.z < MAX_XYZ.z &&
-                Self::enemy_check_if_chunk_is_valid_pos(
-                    curr_pos,
-                    enemy_handle,
-                    &half_hitbox,
-                    &other_positions,
-                    &enemies.size,
-                    &enemies.e_type,
-                    chunk
-                )
-            {
-                pos.0.z = curr_pos.0.z - half_hitbox.z * vel.z.signum();
+        }
+        let can_step = was_grounded || body.grounded;
+        if Self::sweep_stepping(&mut body.pos.0, half, 0, delta.x, can_step, colliders) {
+            body.vel.x = 0.0;
+        }
+        if Self::sweep_stepping(&mut body.pos.0, half, 2, delta.z, can_step, colliders) {
+            body.vel.z = 0.0;
+        }
+
+        let pos = &mut body.pos.0;
+        let feet = pos.y - half.y;
+        if let Some((surface, ramp)) = world_layout.ramp_surface(vec3(pos.x, feet, pos.z), block_registry) {
+            let depth = surface - feet; // how far the feet are inside the slope
+            let rising = body.vel.y > 0.0;
+            let lift = depth > 0.0 && depth <= STEP_HEIGHT;
+            // walking down a ramp keeps the body on it instead of running off into the air
+            let follow = !rising && was_grounded && depth <= 0.0 && -depth < RAMP_SNAP;
+            if lift || follow {
+                Self::sweep(pos, half, 1, depth + SKIN, colliders);
+                if !rising {
+                    body.vel.y = 0.0;
+                    body.grounded = true;
+                    ground_block = Some(ramp);
+                }
             }
-            let prev_tiles = &Enemies::get_occupied_tiles(&initial_pos, &half_hitbox);
-            pos.0 = pos.0.clamp(vec3(1.0, 0.0, 1.0), Vec3::splat((CHUNK_SIZE as f32) - 1.0));
-            Self::update_enemy_world_position(
-                prev_tiles,
-                &Enemies::get_occupied_tiles(&pos, &half_hitbox),
-                chunk,
-                EntityType::Enemy(enemy_handle)
-            );
         }
+        // the sweeps already stop at the border, this only catches bodies that got pushed past it
+        let (min, max) = world_layout.playable_aabb();
+        body.pos.0 = body.pos.0.clamp(min + half, max - half);
+        ground_block
     }
 
-    fn enemy_check_if_chunk_is_valid_pos(
-        pos: ChunkVec3,
-        handle: EnemyHandle,
-        half_hb1: &Vec3,
-        other_positions: &Vec<ChunkVec3>,
-        other_sizes: &Vec<PossibleEnemySizes>,
-        other_types: &Vec<EnemyType>,
-        chunk: &[[[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize]; CHUNK_SIZE as usize]
-    ) -> bool {
-        let chunk_pos = pos.to_chunk();
-        let entities_in_chunk =
-            &chunk[chunk_pos.x as usize][chunk_pos.y as usize][chunk_pos.z as usize];
-
-        for entity in entities_in_chunk {
-            match entity {
-                EntityType::Enemy(h_other) => {
-                    if *h_other == handle {
-                        continue;
-                    }
-                    if other_types[h_other.0 as usize] == EnemyType::Empty {
-                        // unnecessary, but keep to make sure it doesnt break, -> theres hould never be a reference to an empty enemy in the world layout
-                        continue;
-                    }
-                    let half_hb2 =
-                        Enemies::get_hitbox_from_size(other_sizes[h_other.0 as usize]) * 0.5;
-                    let pos2 = other_positions[h_other.0 as usize];
-                    let actual_hb_for_movement = *half_hb1 * 0.5;
-                    let actual_hb1_for_movement = half_hb2 * 0.5;
-                    if
-                        Self::intersect_hitbox(
-                            &pos.0,
-                            &actual_hb_for_movement,
-                            &pos2.0,
-                            &actual_hb1_for_movement
-                        )
-                    {
-                        return false;
-                    }
-                }
-                EntityType::Player => {
-                    return false;
-                }
+    /// enemies walk towards the player, farther ones quicker up to ENEMY_DEFAULT_MOVE_SPEED, while keeping some
+    /// room between each other and going around walls. The velocity only turns towards that gradually, so knockback
+    /// takes a moment to wear off
+    fn steer_enemy(body: &mut Body, colliders: &Colliders) {
+        let to_player = vec3_no_y(colliders.player.pos.0 - body.pos.0);
+        // make farther enemies quicker, but dont overdo it
+        let pursuit = (to_player * 0.3).clamp_length_max(ENEMY_DEFAULT_MOVE_SPEED);
+        let separation = Self::separation(body, to_player.normalize_or_zero(), colliders);
+        let target = Self::avoid_walls(body, pursuit + separation * ENEMY_SEPARATION_STRENGTH, colliders);
+        let current = vec3_no_y(body.vel);
+        let steered = current + (target - current) * (ENEMY_STEERING * PHYSICS_FRAME_TIME).min(1.0);
+        body.vel.x = steered.x;
+        body.vel.z = steered.z;
+    }
 
-                EntityType::SolidBlock | EntityType::InteractableBlock(_) => {
-                    return false;
-                }
+    /// points away from every enemy closer than ENEMY_SEPARATION, the closer the stronger. Enemies stuck behind
+    /// another one on the way to the player also get pushed sideways, so crowds fan out around the player
+    /// instead of queuing up
+    fn separation(body: &Body, to_player: Vec3, colliders: &Colliders) -> Vec3 {
+        let EntityType::Enemy(own) = colliders.entity else {
+            return Vec3::ZERO;
+        };
+        // enemies on the exact same spot, or exactly in line, need some side to pick
+        let fallback = Self::rotate_y(vec3(1.0, 0.0, 0.0), own.0 as f32 * 2.4);
+        let side = vec3(-to_player.z, 0.0, to_player.x);
+        let mut push = Vec3::ZERO;
+        let reach = Vec3::splat(ENEMY_SEPARATION);
+        for entity in colliders.world_layout.entities_in_aabb(body.min() - reach, body.max() + reach) {
+            if !matches!(entity, EntityType::Enemy(_)) {
+                continue;
+            }
+            let Some(other) = colliders.body_of(entity) else {
+                continue;
+            };
+            if (body.pos.0.y - other.pos.0.y).abs() >= body.half_extents.y + other.half_extents.y {
+                continue; // one stands on top of the other, or on a different level
+            }
+            let offset = vec3_no_y(body.pos.0 - other.pos.0);
+            let distance = offset.length();
+            let gap = body.half_extents.x + other.half_extents.x + ENEMY_SEPARATION;
+            if distance >= gap {
+                continue;
+            }
+            let away = if distance > SKIN { offset / distance } else { fallback };
+            let weight = 1.0 - distance / gap;
+            push += away * weight;
+            if offset.dot(to_player) < 0.0 {
+                // the other one is in front, walk around it on the side we are already on
+                let sign = if side.dot(offset).abs() > SKIN { side.dot(offset).signum() } else { side.dot(fallback).signum() };
+                push += side * sign * weight;
             }
         }
-        return true;
+        push
     }
 
-    fn intersect_hitbox(p1: &Vec3, half_hb1: &Vec3, p2: &Vec3, half_hb2: &Vec3) -> bool {
-        let x_overlap = (p1.x - p2.x).abs() <= half_hb1.x + half_hb2.x;
-        let y_overlap = (p1.y - p2.y).abs() <= half_hb1.y + half_hb2.y;
-        let z_overlap = (p1.z - p2.z).abs() <= half_hb1.z + half_hb2.z;
-        x_overlap && y_overlap && z_overlap
+    /// walks along a wall too high to step on that is right in front, instead of pressing against it
+    fn avoid_walls(body: &Body, target: Vec3, colliders: &Colliders) -> Vec3 {
+        let speed = target.length();
+        if speed <= SKIN {
+            return target;
+        }
+        // the whole footprint moved ahead, so a wall corner the body still overlaps counts as in the way
+        let above_step = body.pos.0.y - body.half_extents.y + STEP_HEIGHT + 0.5;
+        let blocked = |dir: Vec3| {
+            let ahead = dir * ENEMY_AVOID_DISTANCE;
+            let min = vec3(body.min().x + SKIN, above_step, body.min().z + SKIN) + ahead;
+            let max = vec3(body.max().x - SKIN, above_step, body.max().z - SKIN) + ahead;
+            WorldLayout::tiles_in_aabb(min, max).any(|tile| Self::blocked_tile(tile, colliders.world_layout))
+        };
+        let dir = target / speed;
+        if !blocked(dir) {
+            return target;
+        }
+        // keep going the way the enemy already walks along the wall, otherwise it would turn around halfway.
+        // Otherwise take the side the player is on, and with the player straight behind the wall every other
+        // enemy picks the other side, so a crowd splits up in front of it
+        let wall_along_z = dir.x.abs() > SKIN && blocked(vec3(dir.x.signum(), 0.0, 0.0));
+        let along = if wall_along_z { vec3(0.0, 0.0, 1.0) } else { vec3(1.0, 0.0, 0.0) };
+        let moving = body.vel.dot(along);
+        let wanted = dir.dot(along);
+        let preferred = match colliders.entity {
+            _ if moving.abs() > SKIN => moving.signum(),
+            _ if wanted.abs() > SKIN => wanted.signum(),
+            EntityType::Enemy(h) if h.0 % 2 == 1 => -1.0,
+            _ => 1.0,
+        };
+        for side in [preferred, -preferred] {
+            if !blocked(along * side) {
+                return along * side * speed;
+            }
+        }
+        target
     }
 
-    fn update_world_position(
-        chunk: &mut [
-            [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-            CHUNK_SIZE as usize
-        ],
-        entity_type: EntityType,
-        new_pos: &ChunkVec3,
-        prev_pos: &ChunkVec3,
-        half_hitbox: &Vec3
-    ) {
-        let start = ChunkVec3(prev_pos.0 - *half_hitbox).to_chunk();
-        let end = ChunkVec3(prev_pos.0 + *half_hitbox).to_chunk();
-        for x in start.x..=end.x {
-            for y in start.y..=end.y {
-                for z in start.z..=end.z {
-                    if x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE {
-                        chunk[x as usize][y as usize][z as usize].retain(|e| *e != entity_type);
-                    }
-                }
+    fn rotate_y(dir: Vec3, angle: f32) -> Vec3 {
+        let (sin, cos) = angle.sin_cos();
+        vec3(dir.x * cos - dir.z * sin, dir.y, dir.x * sin + dir.z * cos)
+    }
+
+    /// dashes go along the wish direction, or where the player looks when no key is held. While dashing the
+    /// velocity is fixed and gravity starts over every frame, collision still goes through the usual sweep
+    fn update_dash(player: &mut Player) {
+        let dash = &mut player.dash;
+        dash.cooldown = (dash.cooldown - PHYSICS_FRAME_TIME).max(0.0);
+        dash.invulnerable = (dash.invulnerable - PHYSICS_FRAME_TIME).max(0.0);
+        if dash.requested && dash.cooldown <= 0.0 {
+            let facing = vec3(player.yaw.cos(), 0.0, player.yaw.sin());
+            dash.dir = if player.input.wish_dir == Vec3::ZERO { facing } else { player.input.wish_dir };
+            dash.time_left = DASH_TIME;
+            dash.cooldown = DASH_COOLDOWN;
+            dash.invulnerable = DASH_INVULNERABILITY;
+        }
+        dash.requested = false;
+        if !dash.is_dashing() {
+            return;
+        }
+        dash.time_left -= PHYSICS_FRAME_TIME;
+        if dash.is_dashing() {
+            player.body.vel = dash.dir * DASH_SPEED;
+        } else {
+            // dont keep flying at dash speed through the air afterwards
+            player.body.vel = dash.dir * SPRINT_SPEED + vec3(0.0, player.body.vel.y, 0.0);
+        }
+    }
+
+    /// crouching keeps the feet where they are, standing up only works with enough room above the head
+    fn update_crouch(player: &mut Player, world_layout: &WorldLayout) {
+        let height_difference = PLAYER_HALF_HITBOX.y - PLAYER_CROUCH_HALF_HEIGHT;
+        let body = &mut player.body;
+        if player.input.crouch && !player.crouching {
+            player.crouching = true;
+            body.pos.0.y -= height_difference;
+        } else if !player.input.crouch && player.crouching {
+            let standing = body.pos.0 + vec3(0.0, height_difference, 0.0);
+            let half_hitbox = PLAYER_HALF_HITBOX - SKIN;
+            let blocked = WorldLayout::tiles_in_aabb(standing - half_hitbox, standing + half_hitbox).any(|tile| {
+                Self::blocked_tile(tile, world_layout)
+            });
+            if !blocked {
+                player.crouching = false;
+                body.pos.0 = standing;
             }
         }
+        body.half_extents = Player::half_hitbox_for(player.crouching);
+    }
 
-        let start = ChunkVec3(new_pos.0 - *half_hitbox).to_chunk();
-        let end = ChunkVec3(new_pos.0 + *half_hitbox).to_chunk();
-        for x in start.x..=end.x {
-            for y in start.y..=end.y {
-                for z in start.z..=end.z {
-                    if x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE {
-                        chunk[x as usize][y as usize][z as usize].push(entity_type);
-                    }
-                }
+    /// accelerates the horizontal velocity towards the wish direction, ground friction slows down
+    /// everything else. Both scale with the friction of the ground block, in the air only AIR_CONTROL is left
+    fn accelerate(player: &mut Player, ground_friction: f32) {
+        let input = player.input;
+        let target_speed = if player.crouching {
+            CROUCH_SPEED
+        } else if input.sprint {
+            SPRINT_SPEED
+        } else {
+            WALK_SPEED
+        };
+        let mut horizontal = vec3(player.body.vel.x, 0.0, player.body.vel.z);
+        if player.body.grounded {
+            let speed = horizontal.length();
+            if speed > 0.0 {
+                let drop = speed * GROUND_FRICTION * ground_friction * PHYSICS_FRAME_TIME;
+                horizontal *= (speed - drop).max(0.0) / speed;
             }
         }
+        let missing = target_speed - horizontal.dot(input.wish_dir);
+        if missing > 0.0 {
+            let control = if player.body.grounded { ground_friction } else { AIR_CONTROL };
+            let gain = GROUND_ACCELERATION * target_speed * control * PHYSICS_FRAME_TIME;
+            horizontal += input.wish_dir * gain.min(missing);
+        }
+        player.body.vel.x = horizontal.x;
+        player.body.vel.z = horizontal.z;
     }
-    fn update_enemy_world_position(
-        prev_tiles: &Vec<ChunkPos>,
-        occupied_tiles: &Vec<ChunkPos>,
-        chunk: &mut [
-            [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-            CHUNK_SIZE as usize
-        ],
-        enemy_type: EntityType
-    ) {
-        debug_assert!(match enemy_type {
-            EntityType::Enemy(_) => true,
-            _ => false,
+
+    /// jumps only start on the ground or shortly after leaving it, presses right before landing are kept
+    /// until the player touches down and letting go of the key early cuts the jump short
+    fn update_jump(body: &mut Body, jump: &mut JumpState) {
+        let vel = &mut body.vel;
+        if body.grounded {
+            jump.since_grounded = 0.0;
+        } else {
+            jump.since_grounded += PHYSICS_FRAME_TIME;
+        }
+        if jump.buffered > 0.0 && jump.since_grounded < COYOTE_TIME {
+            vel.y = JUMP_STRENGTH;
+            jump.buffered = 0.0;
+            jump.since_grounded = COYOTE_TIME; // no second jump out of the same coyote window
+            body.grounded = false;
+            jump.rising = true;
+        }
+        jump.buffered = (jump.buffered - PHYSICS_FRAME_TIME).max(0.0);
+        if jump.rising && (!jump.held || vel.y <= 0.0) {
+            if vel.y > 0.0 {
+                vel.y *= JUMP_CUT;
+            }
+            jump.rising = false;
+        }
+    }
+
+    /// moves an entity walking into a linked portal out in front of the exit portal and turns its velocity along,
+    /// returns the yaw rotation that got applied. The caller keeps `world_layout` up to date
+    fn teleport(
+        body: &mut Body,
+        interactable_blocks: &InteractableBlocks,
+        world_layout: &WorldLayout
+    ) -> Option<f32> {
+        let (pos, vel, half_hitbox) = (&mut body.pos, &mut body.vel, &body.half_extents);
+        let reach = *half_hitbox + PORTAL_REACH;
+        let touched = world_layout.entities_in_aabb(pos.0 - reach, pos.0 + reach);
+        let entry = interactable_blocks.portal_in(&touched)?;
+        let entry_pos = interactable_blocks.positions[entry as usize].0;
+        if (entry_pos - pos.0).dot(*vel) <= 0.0 {
+            return None; // only walking into it counts, otherwise we would bounce right back after leaving the exit
+        }
+        let exit = interactable_blocks.exits[entry as usize]?;
+        let exit_pos = interactable_blocks.positions[exit as usize].0;
+        let entry_facing = interactable_blocks.facing(entry)?;
+        let exit_facing = interactable_blocks.facing(exit)?;
+
+        let out = exit_facing.dir();
+        let distance = 0.5 + (out * *half_hitbox).abs().max_element() + PORTAL_REACH * 2.0;
+        let target = exit_pos + out * distance + Vec3::new(0.0, pos.0.y - entry_pos.y, 0.0);
+        let blocked = WorldLayout::tiles_in_aabb(target - *half_hitbox, target + *half_hitbox).any(|tile| {
+            !world_layout.in_bounds(tile) || world_layout.is_solid(tile)
         });
-        for tile in prev_tiles {
-            chunk[tile.x as usize][tile.y as usize][tile.z as usize].retain(|e| *e != enemy_type);
+        if blocked {
+            return None;
+        }
+
+        // walking in against the entry facing has to come out along the exit facing
+        let rotation = exit_facing.yaw() - entry_facing.yaw() + PI;
+        *vel = Self::rotate_y(*vel, rotation);
+        pos.0 = target;
+        body.prev_pos = body.pos; // no sliding through the world between the portals
+        Some(rotation)
+    }
+
+    /// upward velocity needed to reach the given height in tiles
+    fn launch_speed(height: f32) -> f32 {
+        (2.0 * -GRAVITY * height.max(0.0)).sqrt()
+    }
+
+    fn update_world_position(world_layout: &mut WorldLayout, entity_type: EntityType, prev: &Body, new: &Body) {
+        for tile in WorldLayout::tiles_in_aabb(prev.min(), prev.max()) {
+            world_layout.remove(tile, entity_type);
         }
-        for tile in occupied_tiles {
-            chunk[tile.x as usize][tile.y as usize][tile.z as usize].push(enemy_type);
+        for tile in WorldLayout::tiles_in_aabb(new.min(), new.max()) {
+            world_layout.insert(tile, entity_type);
         }
     }
 
-    fn player_check_if_chunk_is_valid_pos(
-        pos: &ChunkVec3,
-        half_hb1: &Vec3,
-        enemy_pos: &Vec<ChunkVec3>,
-        enemy_sizes: &Vec<PossibleEnemySizes>,
-        chunk: &[[[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize]; CHUNK_SIZE as usize]
-    ) -> bool {
-        let chunk_pos = pos.to_chunk();
-        let entities_in_chunk =
-            &chunk[chunk_pos.x as usize][chunk_pos.y as usize][chunk_pos.z as usize];
-
-        for entity in entities_in_chunk {
-            match entity {
-                EntityType::Enemy(h_other) => {
-                    let half_hb2 =
-                        Enemies::get_hitbox_from_size(enemy_sizes[h_other.0 as usize]) * 0.5;
-                    let pos2 = enemy_pos[h_other.0 as usize];
-                    if Self::intersect_hitbox(&pos.0, half_hb1, &pos2.0, &half_hb2) {
-                        return false;
-                    }
-                }
-                EntityType::Player => {
-                    continue;
-                }
-                EntityType::SolidBlock | EntityType::InteractableBlock(_) => {
-                    return false;
-                }
+    /// moves a hitbox by `delta` along one axis (0 x, 1 y, 2 z) and stops it right in front of the first
+    /// blocked tile or other body in the way. Every tile the box sweeps through gets checked, so fast moves
+    /// cant tunnel through thin walls. Returns true if the move got cut short
+    fn sweep(pos: &mut Vec3, half: Vec3, axis: usize, delta: f32, colliders: &Colliders) -> bool {
+        if delta == 0.0 {
+            return false;
+        }
+        let world_layout = colliders.world_layout;
+        let sign = delta.signum();
+        let face = pos[axis] + sign * half[axis];
+        // shrunk by the skin, so tiles and bodies the box only touches dont count
+        let min = *pos - half + SKIN;
+        let max = *pos + half - SKIN;
+        let mut allowed = delta;
+
+        let first = Self::tile_index(face - sign * SKIN) + (sign as i32);
+        let last = Self::tile_index(face + delta);
+        let mut layer = first;
+        while (layer - last) * (sign as i32) <= 0 {
+            let (mut layer_min, mut layer_max) = (min, max);
+            layer_min[axis] = layer as f32;
+            layer_max[axis] = layer as f32;
+            let blocked = WorldLayout::tiles_in_aabb(layer_min, layer_max).any(|tile| {
+                Self::blocked_tile(tile, world_layout)
+            });
+            if blocked {
+                allowed = (layer as f32) - sign * 0.5 - face - sign * SKIN;
+                break;
+            }
+            layer += sign as i32;
+        }
+
+        let (mut swept_min, mut swept_max) = (min, max);
+        swept_min[axis] = swept_min[axis].min(min[axis] + allowed);
+        swept_max[axis] = swept_max[axis].max(max[axis] + allowed);
+        for entity in world_layout.entities_in_aabb(swept_min, swept_max) {
+            let Some(other) = colliders.body_of(entity) else {
+                continue;
+            };
+            let (other_pos, other_half) = (other.pos.0, other.half_extents);
+            let overlaps_across = (0..3)
+                .filter(|&i| i != axis)
+                .all(|i| (pos[i] - other_pos[i]).abs() < half[i] + other_half[i]);
+            let distance = other_pos[axis] - sign * other_half[axis] - face;
+            // bodies that already overlap dont block each other, otherwise they could never get apart again
+            if overlaps_across && distance * sign >= 0.0 && distance.abs() < allowed.abs() + SKIN {
+                allowed = distance - sign * SKIN;
             }
         }
-        return true;
+
+        if allowed * sign < 0.0 {
+            allowed = 0.0;
+        }
+        pos[axis] += allowed;
+        allowed != delta
     }
-    fn check_collision_player(
-        pos: &ChunkVec3,
-        enemies: &Enemies,
-        chunk: &[[[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize]; CHUNK_SIZE as usize]
+
+    /// like `sweep`, but a body on the ground walks up ledges of up to STEP_HEIGHT tiles
+    /// instead of stopping in front of them
+    fn sweep_stepping(
+        pos: &mut Vec3,
+        half: Vec3,
+        axis: usize,
+        delta: f32,
+        can_step: bool,
+        colliders: &Colliders
     ) -> bool {
-        if pos.0.x < 0.0 || pos.0.z < 0.0 || pos.0.y < 0.0 {
-            return true;
+        let start = *pos;
+        let blocked = Self::sweep(pos, half, axis, delta, colliders);
+        if !blocked || !can_step {
+            return blocked;
+        }
+        let mut stepped = start;
+        Self::sweep(&mut stepped, half, 1, STEP_HEIGHT, colliders);
+        let climbed = stepped.y - start.y;
+        let still_blocked = Self::sweep(&mut stepped, half, axis, delta, colliders);
+        Self::sweep(&mut stepped, half, 1, -climbed, colliders);
+        if (stepped[axis] - start[axis]).abs() > (pos[axis] - start[axis]).abs() + SKIN {
+            *pos = stepped;
+            return still_blocked;
         }
-        let chunk_pos = pos.to_chunk(); // only cast if we know its a safe usize
-        if
-            chunk_pos.x >= CHUNK_SIZE - 1 ||
-            chunk_pos.z >= CHUNK_SIZE - 1 ||
-            chunk_pos.y >= CHUNK_SIZE - 1
-        {
+        blocked
+    }
+
+    /// the world border, solid blocks and interactables stop every body
+    fn blocked_tile(tile: ChunkPos, world_layout: &WorldLayout) -> bool {
+        if !world_layout.is_playable(tile) {
             return true;
         }
+        world_layout.is_solid(tile) ||
+            world_layout
+                .entities_at(tile)
+                .iter()
+                .any(|entity| matches!(entity, EntityType::InteractableBlock(_)))
+    }
+
+    /// block under the middle of the body, or under any other part of its feet,
+    /// and the entities in the tiles right below them
+    fn ground(body: &Body, world_layout: &WorldLayout) -> (Option<BlockTypeId>, Vec<EntityType>) {
+        let (pos, half) = (body.pos.0, body.half_extents);
+        let below = Self::tile_index(pos.y - half.y - SKIN * 2.0) as f32;
+        let min = Vec3::new(pos.x - half.x + SKIN, below, pos.z - half.z + SKIN);
+        let max = Vec3::new(pos.x + half.x - SKIN, below, pos.z + half.z - SKIN);
+        let center = ChunkVec3(Vec3::new(pos.x, below, pos.z)).to_chunk();
+        let ground_block = world_layout
+            .block_at(center)
+            .or_else(|| WorldLayout::tiles_in_aabb(min, max).find_map(|tile| world_layout.block_at(tile)));
+        (ground_block, world_layout.entities_in_aabb(min, max))
+    }
 
-        let res = Self::player_check_if_chunk_is_valid_pos(
-            pos,
-            &Vec3::splat(0.5),
-            &enemies.positions,
-            &enemies.size,
-            chunk
-        );
-        !res
+    /// tiles are centered on integer coordinates
+    fn tile_index(coord: f32) -> i32 {
+        coord.round() as i32
     }
 }
@@ -0,0 +1,254 @@
+use std::{ fmt, fs, io, path::Path };
+
+use shared::types::{ BlockRegistry, TickInput };
+
+use crate::{
+    floor::FloorGenerator,
+    level::{ Level, LevelError },
+    save::{ Persist, SaveError, SaveReader, SaveWriter },
+    spawning::SpawningSystem,
+    World,
+};
+
+// Replay files are little endian binary like saves: the magic, the format version, how the run started and
+// its seed, one `TickInput` per physics step and the fingerprint of the run after the last step.
+// Bump REPLAY_VERSION whenever that layout changes. Changes to the simulation itself break the fingerprint
+// of older replays instead, record them again once the new behavior is what we want.
+// Arena replays keep a hash of the level they started on, an edited level file fails to start them.
+
+pub const REPLAY_VERSION: u32 = 2;
+const MAGIC: &[u8; 4] = b"ANRP";
+
+/// where the first level of a run comes from, the seed does the rest
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunStart {
+    Floor, // generated floor run
+    Level {
+        path: String, // .lvl or .vox arena
+        hash: u64, // see `level_hash`, of the arena when the run started
+    },
+}
+
+/// a run from its start, replaying it steps a fresh world through the same inputs
+pub struct Replay {
+    pub start: RunStart,
+    pub seed: u64,
+    pub inputs: Vec<TickInput>,
+    pub fingerprint: u64, // see `fingerprint`, of the run after the last input
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    NotAReplay,
+    UnsupportedVersion(u32),
+    Corrupt(SaveError),
+    Level(LevelError),
+    LevelChanged(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "could not access replay: {}", err),
+            ReplayError::NotAReplay => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(version) =>
+                write!(f, "replay version {} is not supported, expected {}", version, REPLAY_VERSION),
+            ReplayError::Corrupt(err) => write!(f, "corrupt replay: {}", err),
+            ReplayError::Level(err) => write!(f, "could not start the recorded run: {}", err),
+            ReplayError::LevelChanged(path) => write!(f, "{} changed since the run was recorded", path),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl From<SaveError> for ReplayError {
+    fn from(err: SaveError) -> Self {
+        ReplayError::Corrupt(err)
+    }
+}
+
+impl From<LevelError> for ReplayError {
+    fn from(err: LevelError) -> Self {
+        ReplayError::Level(err)
+    }
+}
+
+impl Persist for TickInput {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.movement);
+        w.write(&self.look);
+        w.write(&self.jump_pressed);
+        w.write(&self.jump_held);
+        w.write(&self.sprint);
+        w.write(&self.crouch);
+        w.write(&self.dash);
+        w.write(&self.shoot);
+        w.write(&self.next_weapon);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(TickInput {
+            movement: r.read()?,
+            look: r.read()?,
+            jump_pressed: r.read()?,
+            jump_held: r.read()?,
+            sprint: r.read()?,
+            crouch: r.read()?,
+            dash: r.read()?,
+            shoot: r.read()?,
+            next_weapon: r.read()?,
+        })
+    }
+}
+
+impl Persist for RunStart {
+    fn write(&self, w: &mut SaveWriter) {
+        match self {
+            RunStart::Floor => w.write(&0u8),
+            RunStart::Level { path, hash } => {
+                w.write(&1u8);
+                w.write(path);
+                w.write(hash);
+            }
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(RunStart::Floor),
+            1 => Ok(RunStart::Level { path: r.read()?, hash: r.read()? }),
+            _ => Err(SaveError::Corrupt("unknown run start")),
+        }
+    }
+}
+
+impl RunStart {
+    /// an arena run starting on the level at `path` as it is right now
+    pub fn level(path: &str) -> Result<RunStart, LevelError> {
+        let level = Level::load_any(path, &BlockRegistry::default())?;
+        Ok(RunStart::Level { path: path.to_owned(), hash: level_hash(&level) })
+    }
+}
+
+// FNV-1a, unlike the std hashers it stays the same across processes and platforms
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+/// hash of a loaded level, a .vox arena changes with its palette as well
+pub fn level_hash(level: &Level) -> u64 {
+    let mut w = SaveWriter::default();
+    w.write(level);
+    fnv1a(&w.bytes)
+}
+
+/// hash of everything the simulation carries from one step to the next, two runs with the same
+/// fingerprint played out bit for bit the same. Animations follow the rendered frames
+/// and are left out
+pub fn fingerprint(world: &World, spawner: &SpawningSystem) -> u64 {
+    let mut w = SaveWriter::default();
+    let player = &world.player;
+    w.write(&player.body);
+    w.write(&player.yaw);
+    w.write(&player.pitch);
+    w.write(&player.curr_weapon.0);
+    w.write(&player.health);
+    w.write(&player.jump);
+    w.write(&player.crouching);
+    w.write(&player.dash);
+    w.write(&world.enemies.bodies);
+    w.write(&world.enemies.size);
+    w.write(&world.enemies.healths);
+    w.write(&world.enemies.e_type);
+    w.write(&world.solid_blocks);
+    w.write(&world.interactable_blocks);
+    w.write(&world.world_layout);
+    w.write(&world.world_event_queue);
+    w.write(&world.level);
+    w.write(&world.rooms);
+    w.write(&world.rng);
+    w.write(spawner);
+    fnv1a(&w.bytes)
+}
+
+impl Replay {
+    pub fn new(start: RunStart, seed: u64) -> Self {
+        Replay { start, seed, inputs: Vec::new(), fingerprint: 0 }
+    }
+
+    /// the world and spawner of the run before its first step
+    pub fn start_run(&self) -> Result<(World, SpawningSystem), ReplayError> {
+        let level = match &self.start {
+            RunStart::Floor => FloorGenerator::generate(self.seed, 1),
+            RunStart::Level { path, hash } => {
+                let mut level = Level::load_any(path, &BlockRegistry::default())?;
+                if level_hash(&level) != *hash {
+                    return Err(ReplayError::LevelChanged(path.clone()));
+                }
+                level.meta.seed = Some(self.seed);
+                level
+            }
+        };
+        Ok((World::from_level(&level), SpawningSystem::new()))
+    }
+
+    /// steps a fresh run through every input without a window,
+    /// true if it ended up exactly where the recording did
+    pub fn verify(&self) -> Result<bool, ReplayError> {
+        let (mut world, mut spawner) = self.start_run()?;
+        for input in &self.inputs {
+            world.update(&mut spawner, input);
+        }
+        Ok(fingerprint(&world, &spawner) == self.fingerprint)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = SaveWriter::default();
+        w.put(MAGIC);
+        w.write(&REPLAY_VERSION);
+        w.write(&self.start);
+        w.write(&self.seed);
+        w.write(&self.inputs);
+        w.write(&self.fingerprint);
+        w.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, ReplayError> {
+        let mut r = SaveReader::new(bytes);
+        if &r.take::<4>().map_err(|_| ReplayError::NotAReplay)? != MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+        let version = r.read::<u32>()?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let replay = Replay {
+            start: r.read()?,
+            seed: r.read()?,
+            inputs: r.read()?,
+            fingerprint: r.read()?,
+        };
+        if !r.is_empty() {
+            return Err(SaveError::Corrupt("trailing data after the replay").into());
+        }
+        Ok(replay)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        Replay::from_bytes(&fs::read(path)?)
+    }
+}
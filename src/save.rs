@@ -0,0 +1,1029 @@
+use std::{ collections::VecDeque, fmt, fs, io, path::Path, time::Duration };
+
+use shared::{
+    config::BOSS_EXPLOSION_RADIUS,
+    types::{
+        AnimationCallbackEvent,
+        AnimationState,
+        BlockRegistry,
+        Body,
+        BlockTypeId,
+        Chunk,
+        ChunkCoord,
+        ChunkPos,
+        ChunkVec3,
+        CurrWeapon,
+        DashState,
+        Enemies,
+        EnemyHandle,
+        EnemyType,
+        EntityType,
+        Facing,
+        InteractableBlockType,
+        InteractableBlocks,
+        JumpState,
+        MaxWeapon,
+        MoveInput,
+        Player,
+        PossibleEnemySizes,
+        Rng,
+        RunRng,
+        SolidBlocks,
+        Weapon,
+        WeaponType,
+        WorldEvent,
+        WorldLayout,
+    },
+    Vec2,
+    Vec3,
+};
+
+use crate::{
+    floor::{ Room, RoomKind, RoomState },
+    level::{ check_size, EnemySpawn, Level, LevelMeta },
+    spawning::{ ActiveRoom, RoomSpawnProfile, SpawningSystem },
+    World,
+};
+
+// Save files are little endian binary: the magic, the format version and then the run,
+// written field by field in the order of the `Persist` impls below.
+// Bump SAVE_VERSION whenever that order or any field changes, older files get rejected.
+
+pub const SAVE_VERSION: u32 = 8;
+pub const DEFAULT_SAVE_PATH: &str = "saves/run.sav";
+const MAGIC: &[u8; 4] = b"ANAB";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    NotASave,
+    UnsupportedVersion(u32),
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access save: {}", err),
+            SaveError::NotASave => write!(f, "not a save file"),
+            SaveError::UnsupportedVersion(version) =>
+                write!(f, "save version {} is not supported, expected {}", version, SAVE_VERSION),
+            SaveError::Corrupt(what) => write!(f, "corrupt save: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+#[derive(Default)]
+pub struct SaveWriter {
+    pub bytes: Vec<u8>,
+}
+
+pub struct SaveReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl SaveWriter {
+    pub fn write<T: Persist>(&mut self, value: &T) {
+        value.write(self);
+    }
+    pub(crate) fn put(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+}
+
+impl<'a> SaveReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SaveReader { bytes, pos: 0 }
+    }
+    pub fn read<T: Persist>(&mut self) -> Result<T, SaveError> {
+        T::read(self)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+    pub(crate) fn take<const N: usize>(&mut self) -> Result<[u8; N], SaveError> {
+        let end = self.pos + N;
+        let bytes = self.bytes.get(self.pos..end).ok_or(SaveError::Corrupt("unexpected end of file"))?;
+        self.pos = end;
+        Ok(bytes.try_into().expect("slice has N bytes"))
+    }
+}
+
+/// everything that ends up in a save file
+pub trait Persist: Sized {
+    fn write(&self, w: &mut SaveWriter);
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError>;
+}
+
+macro_rules! persist_number {
+    ($($t:ty),*) => {
+        $(
+            impl Persist for $t {
+                fn write(&self, w: &mut SaveWriter) {
+                    w.put(&self.to_le_bytes());
+                }
+                fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+                    Ok(<$t>::from_le_bytes(r.take()?))
+                }
+            }
+        )*
+    };
+}
+persist_number!(u8, u16, u32, u64, i32, f32);
+
+impl Persist for usize {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&(*self as u64));
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        usize::try_from(r.read::<u64>()?).map_err(|_| SaveError::Corrupt("length does not fit"))
+    }
+}
+
+impl Persist for bool {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&(*self as u8));
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SaveError::Corrupt("invalid bool")),
+        }
+    }
+}
+
+impl Persist for String {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.len());
+        w.put(self.as_bytes());
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let bytes: Vec<u8> = (0..r.read::<usize>()?).map(|_| r.read::<u8>()).collect::<Result<_, _>>()?;
+        String::from_utf8(bytes).map_err(|_| SaveError::Corrupt("invalid text"))
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.len());
+        for item in self {
+            w.write(item);
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let len = r.read::<usize>()?;
+        if len > r.bytes.len() {
+            return Err(SaveError::Corrupt("list longer than the file"));
+        }
+        (0..len).map(|_| r.read::<T>()).collect()
+    }
+}
+
+impl<T: Persist> Persist for VecDeque<T> {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.len());
+        for item in self {
+            w.write(item);
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(r.read::<Vec<T>>()?.into())
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.is_some());
+        if let Some(value) = self {
+            w.write(value);
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        if r.read::<bool>()? { Ok(Some(r.read::<T>()?)) } else { Ok(None) }
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.0);
+        w.write(&self.1);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok((r.read()?, r.read()?))
+    }
+}
+
+impl Persist for Duration {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.as_secs());
+        w.write(&self.subsec_nanos());
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let secs = r.read::<u64>()?;
+        let nanos = r.read::<u32>()?;
+        if nanos >= 1_000_000_000 {
+            return Err(SaveError::Corrupt("invalid duration"));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+impl Persist for Vec3 {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.x);
+        w.write(&self.y);
+        w.write(&self.z);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Vec3::new(r.read()?, r.read()?, r.read()?))
+    }
+}
+
+impl Persist for Vec2 {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.x);
+        w.write(&self.y);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Vec2::new(r.read()?, r.read()?))
+    }
+}
+
+impl Persist for ChunkVec3 {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.0);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(ChunkVec3(r.read()?))
+    }
+}
+
+impl Persist for ChunkPos {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.x);
+        w.write(&self.y);
+        w.write(&self.z);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(ChunkPos::new(r.read()?, r.read()?, r.read()?))
+    }
+}
+
+impl Persist for ChunkCoord {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.x);
+        w.write(&self.y);
+        w.write(&self.z);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(ChunkCoord::new(r.read()?, r.read()?, r.read()?))
+    }
+}
+
+impl Persist for BlockTypeId {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.0);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(BlockTypeId(r.read()?))
+    }
+}
+
+impl Persist for EnemyHandle {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.0);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(EnemyHandle(r.read()?))
+    }
+}
+
+impl Persist for Rng {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.state);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Rng { state: r.read()? })
+    }
+}
+
+impl Persist for RunRng {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.seed);
+        w.write(&self.spawning);
+        w.write(&self.loot);
+        w.write(&self.ai);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(RunRng { seed: r.read()?, spawning: r.read()?, loot: r.read()?, ai: r.read()? })
+    }
+}
+
+impl Persist for EntityType {
+    fn write(&self, w: &mut SaveWriter) {
+        match self {
+            EntityType::Player => w.write(&0u8),
+            EntityType::InteractableBlock(index) => {
+                w.write(&1u8);
+                w.write(index);
+            }
+            EntityType::Enemy(handle) => {
+                w.write(&2u8);
+                w.write(handle);
+            }
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(EntityType::Player),
+            1 => Ok(EntityType::InteractableBlock(r.read()?)),
+            2 => Ok(EntityType::Enemy(r.read()?)),
+            _ => Err(SaveError::Corrupt("unknown entity type")),
+        }
+    }
+}
+
+impl Persist for Facing {
+    fn write(&self, w: &mut SaveWriter) {
+        let tag: u8 = match self {
+            Facing::PosX => 0,
+            Facing::NegX => 1,
+            Facing::PosZ => 2,
+            Facing::NegZ => 3,
+        };
+        w.write(&tag);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(Facing::PosX),
+            1 => Ok(Facing::NegX),
+            2 => Ok(Facing::PosZ),
+            3 => Ok(Facing::NegZ),
+            _ => Err(SaveError::Corrupt("unknown portal side")),
+        }
+    }
+}
+
+impl Persist for InteractableBlockType {
+    fn write(&self, w: &mut SaveWriter) {
+        match self {
+            InteractableBlockType::Portal { link, facing } => {
+                w.write(&0u8);
+                w.write(link);
+                w.write(facing);
+            }
+            InteractableBlockType::Trampoline(height) => {
+                w.write(&1u8);
+                w.write(height);
+            }
+            InteractableBlockType::Stairs => w.write(&2u8),
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(InteractableBlockType::Portal { link: r.read()?, facing: r.read()? }),
+            1 => Ok(InteractableBlockType::Trampoline(r.read()?)),
+            2 => Ok(InteractableBlockType::Stairs),
+            _ => Err(SaveError::Corrupt("unknown interactable block")),
+        }
+    }
+}
+
+impl Persist for PossibleEnemySizes {
+    fn write(&self, w: &mut SaveWriter) {
+        let tag: u8 = match self {
+            PossibleEnemySizes::SMALL => 0,
+            PossibleEnemySizes::MEDIUM => 1,
+            PossibleEnemySizes::LARGE => 2,
+            PossibleEnemySizes::BOSS => 3,
+        };
+        w.write(&tag);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(PossibleEnemySizes::SMALL),
+            1 => Ok(PossibleEnemySizes::MEDIUM),
+            2 => Ok(PossibleEnemySizes::LARGE),
+            3 => Ok(PossibleEnemySizes::BOSS),
+            _ => Err(SaveError::Corrupt("unknown enemy size")),
+        }
+    }
+}
+
+impl Persist for EnemyType {
+    fn write(&self, w: &mut SaveWriter) {
+        let tag: u8 = match self {
+            EnemyType::Flying => 0,
+            EnemyType::Regular => 1,
+            EnemyType::Cube => 2,
+            EnemyType::Empty => 3,
+        };
+        w.write(&tag);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(EnemyType::Flying),
+            1 => Ok(EnemyType::Regular),
+            2 => Ok(EnemyType::Cube),
+            3 => Ok(EnemyType::Empty),
+            _ => Err(SaveError::Corrupt("unknown enemy type")),
+        }
+    }
+}
+
+impl Persist for AnimationState {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.current_step);
+        w.write(&self.max_step);
+        let callback: u8 = match self.callback {
+            AnimationCallbackEvent::KillEnemy => 0,
+            AnimationCallbackEvent::None => 1,
+        };
+        w.write(&callback);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let current_step = r.read()?;
+        let max_step = r.read()?;
+        let callback = match r.read::<u8>()? {
+            0 => AnimationCallbackEvent::KillEnemy,
+            1 => AnimationCallbackEvent::None,
+            _ => {
+                return Err(SaveError::Corrupt("unknown animation callback"));
+            }
+        };
+        Ok(AnimationState { current_step, max_step, callback })
+    }
+}
+
+impl Persist for Weapon {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.damage);
+        w.write(&self.reload_speed);
+        w.write(&self.time_last_reload);
+        let w_type: u8 = match self.w_type {
+            WeaponType::Shotgun => 0,
+        };
+        w.write(&w_type);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let damage = r.read()?;
+        let reload_speed = r.read()?;
+        let time_last_reload = r.read()?;
+        let w_type = match r.read::<u8>()? {
+            0 => WeaponType::Shotgun,
+            _ => {
+                return Err(SaveError::Corrupt("unknown weapon"));
+            }
+        };
+        Ok(Weapon { damage, reload_speed, time_last_reload, w_type })
+    }
+}
+
+impl Persist for Player {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.body);
+        w.write(&self.yaw);
+        w.write(&self.pitch);
+        w.write(&self.weapon_unlocked.0);
+        for weapon in &self.weapons {
+            w.write(weapon);
+        }
+        w.write(&self.curr_weapon.0);
+        w.write(&self.animation_state);
+        w.write(&self.health);
+        w.write(&self.jump);
+        w.write(&self.crouching);
+        w.write(&self.dash);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Player {
+            body: r.read()?,
+            yaw: r.read()?,
+            pitch: r.read()?,
+            weapon_unlocked: MaxWeapon(r.read()?),
+            weapons: [r.read()?],
+            curr_weapon: CurrWeapon(r.read()?),
+            animation_state: r.read()?,
+            health: r.read()?,
+            jump: r.read()?,
+            input: MoveInput::default(), // held keys are read again every frame
+            crouching: r.read()?,
+            dash: r.read()?,
+        })
+    }
+}
+
+impl Persist for Body {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.pos);
+        w.write(&self.vel);
+        w.write(&self.half_extents);
+        w.write(&self.mass);
+        w.write(&self.grounded);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let pos = r.read()?;
+        Ok(Body {
+            pos,
+            prev_pos: pos, // nothing to interpolate from right after loading
+            vel: r.read()?,
+            half_extents: r.read()?,
+            mass: r.read()?,
+            grounded: r.read()?,
+        })
+    }
+}
+
+impl Persist for JumpState {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.since_grounded);
+        w.write(&self.buffered);
+        w.write(&self.held);
+        w.write(&self.rising);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(JumpState {
+            since_grounded: r.read()?,
+            buffered: r.read()?,
+            held: r.read()?,
+            rising: r.read()?,
+        })
+    }
+}
+
+impl Persist for DashState {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.dir);
+        w.write(&self.time_left);
+        w.write(&self.cooldown);
+        w.write(&self.invulnerable);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(DashState {
+            requested: false, // input, see MoveInput
+            dir: r.read()?,
+            time_left: r.read()?,
+            cooldown: r.read()?,
+            invulnerable: r.read()?,
+        })
+    }
+}
+
+impl Persist for Enemies {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.bodies);
+        w.write(&self.animation_state);
+        w.write(&self.size);
+        w.write(&self.healths);
+        w.write(&self.e_type);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Enemies {
+            bodies: r.read()?,
+            animation_state: r.read()?,
+            size: r.read()?,
+            healths: r.read()?,
+            e_type: r.read()?,
+        })
+    }
+}
+
+impl Persist for SolidBlocks {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.positions);
+        w.write(&self.b_types);
+        w.write(&self.hit_points);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(SolidBlocks { positions: r.read()?, b_types: r.read()?, hit_points: r.read()? })
+    }
+}
+
+impl Persist for InteractableBlocks {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.positions);
+        w.write(&self.b_types);
+        w.write(&self.exits);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(InteractableBlocks { positions: r.read()?, b_types: r.read()?, exits: r.read()? })
+    }
+}
+
+impl Persist for Chunk {
+    fn write(&self, w: &mut SaveWriter) {
+        for block in &self.blocks {
+            w.write(block);
+        }
+        for bits in &self.solid {
+            w.write(bits);
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let mut chunk = Chunk::new();
+        for block in chunk.blocks.iter_mut() {
+            *block = r.read()?;
+        }
+        for bits in chunk.solid.iter_mut() {
+            *bits = r.read()?;
+        }
+        Ok(chunk)
+    }
+}
+
+impl Persist for WorldLayout {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.size);
+        w.write(&self.chunks);
+        // sorted, so the same world always gives the same bytes
+        let mut tiles: Vec<(&ChunkPos, &Vec<EntityType>)> = self.entities.iter().collect();
+        tiles.sort_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+        w.write(&tiles.len());
+        for (pos, entities) in tiles {
+            w.write(pos);
+            w.write(entities);
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let size: ChunkCoord = r.read()?;
+        let chunks: Vec<Chunk> = r.read()?;
+        if check_size(size).is_err() || chunks.len() != (size.x * size.y * size.z) as usize {
+            return Err(SaveError::Corrupt("chunk count does not match the world size"));
+        }
+        let mut layout = WorldLayout::new(size);
+        layout.chunks = chunks;
+        for _ in 0..r.read::<usize>()? {
+            let pos = r.read()?;
+            let entities = r.read()?;
+            layout.entities.insert(pos, entities);
+        }
+        Ok(layout)
+    }
+}
+
+impl Persist for WorldEvent {
+    fn write(&self, w: &mut SaveWriter) {
+        match self {
+            WorldEvent::KillEnemy(handle) => {
+                w.write(&0u8);
+                w.write(handle);
+            }
+            WorldEvent::HitEnemy(handle) => {
+                w.write(&1u8);
+                w.write(handle);
+            }
+            WorldEvent::DamagePlayer(amount) => {
+                w.write(&2u8);
+                w.write(amount);
+            }
+            WorldEvent::BlockDamaged(pos) => {
+                w.write(&3u8);
+                w.write(pos);
+            }
+            WorldEvent::BlockDestroyed(pos) => {
+                w.write(&4u8);
+                w.write(pos);
+            }
+            WorldEvent::Explosion(center, radius) => {
+                w.write(&5u8);
+                w.write(center);
+                w.write(radius);
+            }
+            WorldEvent::Impulse(entity, impulse) => {
+                w.write(&6u8);
+                w.write(entity);
+                w.write(impulse);
+            }
+        }
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(WorldEvent::KillEnemy(r.read()?)),
+            1 => Ok(WorldEvent::HitEnemy(r.read()?)),
+            2 => Ok(WorldEvent::DamagePlayer(r.read()?)),
+            3 => Ok(WorldEvent::BlockDamaged(r.read()?)),
+            4 => Ok(WorldEvent::BlockDestroyed(r.read()?)),
+            5 => Ok(WorldEvent::Explosion(r.read()?, r.read()?)),
+            6 => Ok(WorldEvent::Impulse(r.read()?, r.read()?)),
+            _ => Err(SaveError::Corrupt("unknown world event")),
+        }
+    }
+}
+
+impl Persist for LevelMeta {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.name);
+        w.write(&self.author);
+        w.write(&self.seed);
+        w.write(&self.floor);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(LevelMeta { name: r.read()?, author: r.read()?, seed: r.read()?, floor: r.read()? })
+    }
+}
+
+impl Persist for EnemySpawn {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.pos);
+        w.write(&self.size);
+        w.write(&self.e_type);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(EnemySpawn { pos: r.read()?, size: r.read()?, e_type: r.read()? })
+    }
+}
+
+impl Persist for RoomKind {
+    fn write(&self, w: &mut SaveWriter) {
+        let tag: u8 = match self {
+            RoomKind::Start => 0,
+            RoomKind::Combat => 1,
+            RoomKind::Boss => 2,
+        };
+        w.write(&tag);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(RoomKind::Start),
+            1 => Ok(RoomKind::Combat),
+            2 => Ok(RoomKind::Boss),
+            _ => Err(SaveError::Corrupt("unknown room kind")),
+        }
+    }
+}
+
+impl Persist for RoomState {
+    fn write(&self, w: &mut SaveWriter) {
+        let tag: u8 = match self {
+            RoomState::Idle => 0,
+            RoomState::Locked => 1,
+            RoomState::Cleared => 2,
+        };
+        w.write(&tag);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        match r.read::<u8>()? {
+            0 => Ok(RoomState::Idle),
+            1 => Ok(RoomState::Locked),
+            2 => Ok(RoomState::Cleared),
+            _ => Err(SaveError::Corrupt("unknown room state")),
+        }
+    }
+}
+
+impl Persist for Room {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.kind);
+        w.write(&self.state);
+        w.write(&self.min);
+        w.write(&self.max);
+        w.write(&self.doors);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Room { kind: r.read()?, state: r.read()?, min: r.read()?, max: r.read()?, doors: r.read()? })
+    }
+}
+
+impl Persist for Level {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.meta);
+        w.write(&self.size);
+        w.write(&self.player_start);
+        w.write(&self.solid_blocks);
+        w.write(&self.interactable_blocks);
+        w.write(&self.enemy_spawns);
+        w.write(&self.rooms);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Level {
+            meta: r.read()?,
+            size: r.read()?,
+            player_start: r.read()?,
+            solid_blocks: r.read()?,
+            interactable_blocks: r.read()?,
+            enemy_spawns: r.read()?,
+            rooms: r.read()?,
+        })
+    }
+}
+
+impl Persist for RoomSpawnProfile {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.enemy_count);
+        for weight in &self.size_weights {
+            w.write(weight);
+        }
+        w.write(&self.spawn_interval);
+        w.write(&self.boss);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(RoomSpawnProfile {
+            enemy_count: r.read()?,
+            size_weights: [r.read()?, r.read()?, r.read()?, r.read()?],
+            spawn_interval: r.read()?,
+            boss: r.read()?,
+        })
+    }
+}
+
+impl Persist for ActiveRoom {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.profile);
+        w.write(&self.min);
+        w.write(&self.max);
+        w.write(&self.spawned);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(ActiveRoom { profile: r.read()?, min: r.read()?, max: r.read()?, spawned: r.read()? })
+    }
+}
+
+/// the spawn table is code, only the progress through it is saved
+impl Persist for SpawningSystem {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.active_room);
+        w.write(&self.elapsed_time);
+        w.write(&self.difficulty_multiplier);
+        w.write(&self.current_minute);
+        w.write(&self.time_since_last_spawn);
+        w.write(&self.enemies_spawned_this_minute);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        let mut spawner = SpawningSystem::new();
+        spawner.active_room = r.read()?;
+        spawner.elapsed_time = r.read()?;
+        spawner.difficulty_multiplier = r.read()?;
+        spawner.current_minute = r.read()?;
+        spawner.time_since_last_spawn = r.read()?;
+        spawner.enemies_spawned_this_minute = r.read()?;
+        Ok(spawner)
+    }
+}
+
+/// the block registry is code as well
+impl Persist for World {
+    fn write(&self, w: &mut SaveWriter) {
+        w.write(&self.player);
+        w.write(&self.enemies);
+        w.write(&self.solid_blocks);
+        w.write(&self.interactable_blocks);
+        w.write(&self.world_layout);
+        w.write(&self.world_event_queue);
+        w.write(&self.level);
+        w.write(&self.rooms);
+        w.write(&self.rng);
+    }
+    fn read(r: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(World {
+            player: r.read()?,
+            enemies: r.read()?,
+            solid_blocks: r.read()?,
+            interactable_blocks: r.read()?,
+            world_layout: r.read()?,
+            world_event_queue: r.read()?,
+            block_registry: BlockRegistry::default(),
+            level: r.read()?,
+            rooms: r.read()?,
+            rng: r.read()?,
+        })
+    }
+}
+
+pub fn save_run(world: &World, spawner: &SpawningSystem) -> Vec<u8> {
+    let mut w = SaveWriter::default();
+    w.put(MAGIC);
+    w.write(&SAVE_VERSION);
+    w.write(world);
+    w.write(spawner);
+    w.bytes
+}
+
+pub fn load_run(bytes: &[u8]) -> Result<(World, SpawningSystem), SaveError> {
+    let mut r = SaveReader::new(bytes);
+    if &r.take::<4>().map_err(|_| SaveError::NotASave)? != MAGIC {
+        return Err(SaveError::NotASave);
+    }
+    let version = r.read::<u32>()?;
+    if version != SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+    let world = r.read::<World>()?;
+    let spawner = r.read::<SpawningSystem>()?;
+    if !r.is_empty() {
+        return Err(SaveError::Corrupt("trailing data after the run"));
+    }
+    check_run(&world)?;
+    Ok((world, spawner))
+}
+
+/// decoding only checks the format, this checks that the decoded run makes sense. Everything indexed by
+/// a handle or position has to be in range before the layout checker and the simulation get to see it
+fn check_run(world: &World) -> Result<(), SaveError> {
+    let enemies = &world.enemies;
+    let enemy_count = enemies.bodies.len();
+    if
+        [enemies.animation_state.len(), enemies.size.len(), enemies.healths.len(), enemies.e_type.len()]
+            .iter()
+            .any(|&len| len != enemy_count)
+    {
+        return Err(SaveError::Corrupt("enemy arrays differ in length"));
+    }
+    let solid_blocks = &world.solid_blocks;
+    if
+        solid_blocks.b_types.len() != solid_blocks.positions.len() ||
+        solid_blocks.hit_points.len() != solid_blocks.positions.len()
+    {
+        return Err(SaveError::Corrupt("solid block arrays differ in length"));
+    }
+    let interactables = &world.interactable_blocks;
+    let interactable_count = interactables.positions.len();
+    if interactables.b_types.len() != interactable_count || interactables.exits.len() != interactable_count {
+        return Err(SaveError::Corrupt("interactable block arrays differ in length"));
+    }
+    if interactables.exits.iter().flatten().any(|&exit| exit as usize >= interactable_count) {
+        return Err(SaveError::Corrupt("portal exit out of range"));
+    }
+
+    let layout = &world.world_layout;
+    let max = layout.max_xyz();
+    let body_fits = |body: &Body| {
+        let pos = body.pos.0;
+        let half = body.half_extents;
+        pos.is_finite() && pos.cmpge(Vec3::ZERO).all() && pos.cmplt(max).all() &&
+            half.is_finite() && half.cmpgt(Vec3::ZERO).all() && half.cmplt(max).all()
+    };
+    let player = &world.player;
+    if !body_fits(&player.body) {
+        return Err(SaveError::Corrupt("player outside of the world"));
+    }
+    if player.weapon_unlocked.0 > player.weapons.len() || player.curr_weapon.0 >= player.weapons.len() {
+        return Err(SaveError::Corrupt("weapon out of range"));
+    }
+    let live_enemies = enemies.bodies.iter().zip(&enemies.e_type).filter(|(_, e_type)| **e_type != EnemyType::Empty);
+    if live_enemies.map(|(body, _)| body).any(|body| !body_fits(body)) {
+        return Err(SaveError::Corrupt("enemy outside of the world"));
+    }
+
+    let handle_in_range = |entity: EntityType| {
+        match entity {
+            EntityType::Player => true,
+            EntityType::Enemy(h) => (h.0 as usize) < enemy_count,
+            EntityType::InteractableBlock(index) => (index as usize) < interactable_count,
+        }
+    };
+    for event in &world.world_event_queue {
+        let valid = match *event {
+            WorldEvent::KillEnemy(h) | WorldEvent::HitEnemy(h) => handle_in_range(EntityType::Enemy(h)),
+            WorldEvent::Impulse(entity, impulse) => handle_in_range(entity) && impulse.is_finite(),
+            WorldEvent::DamagePlayer(amount) => amount.is_finite(),
+            WorldEvent::BlockDamaged(pos) | WorldEvent::BlockDestroyed(pos) => layout.in_bounds(pos),
+            WorldEvent::Explosion(center, radius) =>
+                layout.in_bounds(center.to_chunk()) && (0.0..=BOSS_EXPLOSION_RADIUS).contains(&radius),
+        };
+        if !valid {
+            return Err(SaveError::Corrupt("world event out of range"));
+        }
+    }
+    if layout.entities.values().flatten().any(|&entity| !handle_in_range(entity)) {
+        return Err(SaveError::Corrupt("entity handle out of range"));
+    }
+
+    // rooms are counted from the level, doors and bounds have to be inside the world
+    if world.rooms.len() != world.level.rooms.len() {
+        return Err(SaveError::Corrupt("room count does not match the level"));
+    }
+    for room in &world.rooms {
+        let ordered = room.min.x <= room.max.x && room.min.y <= room.max.y && room.min.z <= room.max.z;
+        let tiles_in_bounds = [room.min, room.max].iter().chain(&room.doors).all(|&tile| layout.in_bounds(tile));
+        if !ordered || !tiles_in_bounds {
+            return Err(SaveError::Corrupt("room out of range"));
+        }
+    }
+
+    if !world.check_consistency().is_empty() {
+        return Err(SaveError::Corrupt("world layout does not match the entities and blocks"));
+    }
+    Ok(())
+}
+
+pub fn save_to_file<P: AsRef<Path>>(path: P, world: &World, spawner: &SpawningSystem) -> Result<(), SaveError> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, save_run(world, spawner))?;
+    Ok(())
+}
+
+pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<(World, SpawningSystem), SaveError> {
+    load_run(&fs::read(path)?)
+}
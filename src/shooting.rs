@@ -1,6 +1,16 @@
 use shared::{
-    config::CHUNK_SIZE,
-    types::{ ChunkVec3, Enemies, EntityType, Player, WeaponType, WorldEvent },
+    config::SHOTGUN_KNOCKBACK,
+    types::{
+        BlockRegistry,
+        ChunkPos,
+        ChunkVec3,
+        Enemies,
+        EntityType,
+        Player,
+        WeaponType,
+        WorldEvent,
+        WorldLayout,
+    },
     vec3,
     Vec3,
 };
@@ -30,21 +40,23 @@ pub fn shotgun_shoot(
     origin: ChunkVec3,
     target_dir: Vec3,
     enemies: &Enemies,
-    world_layout: &[
-        [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-        CHUNK_SIZE as usize
-    ]
+    world_layout: &WorldLayout,
+    block_registry: &BlockRegistry
 ) -> Option<WorldEvent> {
     let relative_chunk_dist_x = 1.0 / target_dir.x.abs();
     let relative_chunk_dist_y = 1.0 / target_dir.y.abs();
     let relative_chunk_dist_z = 1.0 / target_dir.z.abs();
 
-    let step_x: isize = if target_dir.x > 0.0 { 1 } else { -1 };
-    let step_y: isize = if target_dir.y > 0.0 { 1 } else { -1 };
-    let step_z: isize = if target_dir.z > 0.0 { 1 } else { -1 };
+    let step_x: i32 = if target_dir.x > 0.0 { 1 } else { -1 };
+    let step_y: i32 = if target_dir.y > 0.0 { 1 } else { -1 };
+    let step_z: i32 = if target_dir.z > 0.0 { 1 } else { -1 };
     const ROUNDING_OFFSET: Vec3 = vec3(0.5, 0.5, 0.5);
     let origin = origin + ROUNDING_OFFSET;
-    let curr_chunk = origin.to_chunk();
+    let curr_chunk = ChunkPos::new(
+        origin.0.x.floor() as i32,
+        origin.0.y.floor() as i32,
+        origin.0.z.floor() as i32
+    ); // the traversal below works on tile borders at integer coordinates, so no rounding here
     let mut curr_map_tile_x = curr_chunk.x;
     let mut curr_map_tile_y = curr_chunk.y;
     let mut curr_map_tile_z = curr_chunk.z;
@@ -66,59 +78,57 @@ pub fn shotgun_shoot(
     };
     let mut closest_intersection: Option<(f32, WorldEvent)> = None;
 
-    while
-        curr_map_tile_x < CHUNK_SIZE &&
-        curr_map_tile_y < CHUNK_SIZE &&
-        curr_map_tile_z < CHUNK_SIZE
-    {
+    loop {
+        let curr_tile = ChunkPos::new(curr_map_tile_x, curr_map_tile_y, curr_map_tile_z);
+        if !world_layout.in_bounds(curr_tile) {
+            break;
+        }
         // Check for collision with an entity
-        let entities =
-            &world_layout[curr_map_tile_x as usize][curr_map_tile_y as usize]
-                [curr_map_tile_z as usize];
+        for entity in world_layout.entities_at(curr_tile) {
+            if let EntityType::Enemy(h) = entity {
+                let body = &enemies.bodies[h.0 as usize];
+                let (box_min, box_max) = (body.min(), body.max());
 
-        for entity in entities {
-            match entity {
-                EntityType::Enemy(h) => {
-                    let position = enemies.positions[h.0 as usize];
-                    let hitbox = Enemies::get_hitbox_from_size(enemies.size[h.0 as usize]);
-                    let box_min = position + -hitbox / 2.0;
-                    let box_max = position + hitbox / 2.0;
-
-                    if
-                        let Some(t) = ray_box_intersection(
-                            origin.0 - ROUNDING_OFFSET,
-                            target_dir,
-                            box_min.0,
-                            box_max.0
-                        )
-                    {
-                        let event = WorldEvent::HitEnemy(*h);
-                        closest_intersection = Some(
-                            closest_intersection
-                                .map(|(dist, _)| if t < dist { (t, event) } else { (dist, event) })
-                                .unwrap_or((t, event))
-                        );
-                    } else {
-                        println!("Missed");
-                    }
+                if
+                    let Some(t) = ray_box_intersection(
+                        origin.0 - ROUNDING_OFFSET,
+                        target_dir,
+                        box_min,
+                        box_max
+                    )
+                {
+                    let event = WorldEvent::HitEnemy(*h);
+                    closest_intersection = Some(
+                        closest_intersection
+                            .map(|(dist, _)| if t < dist { (t, event) } else { (dist, event) })
+                            .unwrap_or((t, event))
+                    );
                 }
-                _ => {}
             }
         }
         if closest_intersection.is_some() {
             break;
         }
+        // blocks stop the shot, enemies behind them are safe
+        if let Some(b_type) = world_layout.block_at(curr_tile) {
+            if block_registry.get(b_type).destructible {
+                return Some(WorldEvent::BlockDamaged(curr_tile));
+            }
+            if world_layout.is_solid(curr_tile) {
+                return None;
+            }
+        }
         if dist_side_x < dist_side_y && dist_side_x < dist_side_z {
             // Cross the YZ plane
-            curr_map_tile_x = ((curr_map_tile_x as isize) + step_x) as u8;
+            curr_map_tile_x += step_x;
             dist_side_x += relative_chunk_dist_x;
         } else if dist_side_y < dist_side_z {
             // Cross the XZ plane
-            curr_map_tile_y = ((curr_map_tile_y as isize) + step_y) as u8;
+            curr_map_tile_y += step_y;
             dist_side_y += relative_chunk_dist_y;
         } else {
             // Cross the XY plane
-            curr_map_tile_z = ((curr_map_tile_z as isize) + step_z) as u8;
+            curr_map_tile_z += step_z;
             dist_side_z += relative_chunk_dist_z;
         }
     }
@@ -128,27 +138,26 @@ pub fn shotgun_shoot(
 pub fn shoot(
     player: &mut Player,
     enemies: &Enemies,
-    world_layout: &[
-        [[Vec<EntityType>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
-        CHUNK_SIZE as usize
-    ]
+    world_layout: &WorldLayout,
+    block_registry: &BlockRegistry
 ) -> Vec<WorldEvent> {
     let mut res = Vec::new();
     match player.get_current_weapon().w_type {
         WeaponType::Shotgun => {
-            let front = vec3(
-                player.yaw.cos() * player.pitch.cos(),
-                player.pitch.sin(),
-                player.yaw.sin() * player.pitch.cos()
-            ).normalize();
-            // make player a bit higher, so that when he looks down on smaller opponents he can hit them at their feet
+            let front = player.look_dir();
             let event = shotgun_shoot(
-                player.pos,
+                player.eye_pos(),
                 front,
                 enemies,
-                world_layout
+                world_layout,
+                block_registry
             );
             if let Some(event) = event {
+                if let WorldEvent::HitEnemy(h) = event {
+                    // the blast pushes along the shot, flat so shooting down doesnt drive enemies into the floor
+                    let push = vec3(front.x, 0.0, front.z).normalize_or_zero() * SHOTGUN_KNOCKBACK;
+                    res.push(WorldEvent::Impulse(EntityType::Enemy(h), push));
+                }
                 res.push(event);
             }
         }
@@ -1,13 +1,12 @@
 use std::time::Duration;
 
-use macroquad::rand;
 use shared::{
-    config::{CHUNK_SIZE, WORLD_BORDER},
-    types::{ ChunkVec3, EnemyType, EntityType, PossibleEnemySizes },
+    config::WORLD_BORDER,
+    types::{ ChunkPos, ChunkVec3, Enemies, EnemyType, EntityType, PossibleEnemySizes, Rng },
     Vec3,
 };
 
-use crate::World;
+use crate::{ floor::RoomKind, World };
 
 pub struct SpawnConfig {
     pub enemies_per_minute: usize,
@@ -15,7 +14,25 @@ pub struct SpawnConfig {
     pub boss_spawn_minutes: Vec<u32>,
 }
 
+/// enemies a single room of a floor throws at the player before its doors open again
+#[derive(Clone, Debug)]
+pub struct RoomSpawnProfile {
+    pub enemy_count: usize,
+    pub size_weights: [f32; 4], // Weights for SMALL, MEDIUM, LARGE, BOSS
+    pub spawn_interval: Duration,
+    pub boss: bool, // one boss on top of enemy_count, spawned first
+}
+
+pub struct ActiveRoom {
+    pub profile: RoomSpawnProfile,
+    pub min: ChunkPos,
+    pub max: ChunkPos,
+    pub spawned: usize,
+}
+
+/// when and what to spawn, the random rolls come from the spawning stream of the run, see `RunRng`
 pub struct SpawningSystem {
+    pub active_room: Option<ActiveRoom>, // floors only spawn inside the room the player is locked in
     pub elapsed_time: Duration,
     pub difficulty_multiplier: f32,
     pub current_minute: u32,
@@ -24,9 +41,16 @@ pub struct SpawningSystem {
     pub enemies_spawned_this_minute: usize,
 }
 
+impl Default for SpawningSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SpawningSystem {
     pub fn new() -> Self {
         Self {
+            active_room: None,
             elapsed_time: Duration::from_secs(0),
             difficulty_multiplier: 1.0,
             current_minute: 0,
@@ -104,7 +128,77 @@ impl SpawningSystem {
         }
     }
 
+    /// later rooms and deeper floors use the configs of later minutes
+    pub fn room_profile(&self, floor: u32, room_index: usize, kind: RoomKind) -> RoomSpawnProfile {
+        let stage = (floor as usize + room_index).min(self.spawn_configs.len() - 1);
+        let config = &self.spawn_configs[stage];
+        let spawn_interval = Duration::from_secs(60) / (config.enemies_per_minute as u32);
+        match kind {
+            RoomKind::Start =>
+                RoomSpawnProfile {
+                    enemy_count: 0,
+                    size_weights: config.size_weights,
+                    spawn_interval,
+                    boss: false,
+                },
+            RoomKind::Combat =>
+                RoomSpawnProfile {
+                    enemy_count: 4 + room_index * 2 + (floor as usize) * 2,
+                    size_weights: config.size_weights,
+                    spawn_interval,
+                    boss: false,
+                },
+            RoomKind::Boss =>
+                RoomSpawnProfile {
+                    enemy_count: 3 + (floor as usize),
+                    size_weights: config.size_weights,
+                    spawn_interval: spawn_interval * 2,
+                    boss: true,
+                },
+        }
+    }
+
+    pub fn start_room(&mut self, profile: RoomSpawnProfile, min: ChunkPos, max: ChunkPos) {
+        self.active_room = Some(ActiveRoom { profile, min, max, spawned: 0 });
+        self.time_since_last_spawn = Duration::from_secs(0);
+    }
+
+    /// true once the locked room has spawned everything it is going to
+    pub fn room_finished(&self) -> bool {
+        self.active_room.as_ref().is_none_or(|room| {
+            room.spawned >= room.profile.enemy_count + (room.profile.boss as usize)
+        })
+    }
+
+    fn update_room(&mut self, delta_time: Duration, world: &mut World) {
+        let Some(room) = &self.active_room else {
+            return;
+        };
+        let (min, max, profile) = (room.min, room.max, room.profile.clone());
+        let boss_next = profile.boss && room.spawned == 0;
+        self.time_since_last_spawn += delta_time;
+        if self.room_finished() || self.time_since_last_spawn < profile.spawn_interval {
+            return;
+        }
+        let rng = &mut world.rng.spawning;
+        let position = Self::get_random_position_in_room(rng, min, max);
+        let size = if boss_next {
+            PossibleEnemySizes::BOSS
+        } else {
+            Self::get_random_size(rng, &profile.size_weights)
+        };
+        Self::spawn_at(world, position, size);
+        self.time_since_last_spawn = Duration::from_secs(0);
+        if let Some(room) = &mut self.active_room {
+            room.spawned += 1;
+        }
+    }
+
     pub fn update(&mut self, delta_time: Duration, world: &mut World) {
+        if !world.rooms.is_empty() {
+            self.update_room(delta_time, world);
+            return;
+        }
         self.elapsed_time += delta_time;
         self.time_since_last_spawn += delta_time;
 
@@ -119,33 +213,37 @@ impl SpawningSystem {
         let config =
             &self.spawn_configs
                 [self.current_minute.min((self.spawn_configs.len() as u32) - 1) as usize];
+        let enemies_per_minute = config.enemies_per_minute;
+        let size_weights = config.size_weights;
+        let boss_minute = config.boss_spawn_minutes.contains(&self.current_minute);
 
-
-        let spawn_interval = Duration::from_secs(60) / (config.enemies_per_minute as u32);
+        let spawn_interval = Duration::from_secs(60) / (enemies_per_minute as u32);
 
 
         if
             self.time_since_last_spawn >= spawn_interval &&
-            self.enemies_spawned_this_minute < config.enemies_per_minute
+            self.enemies_spawned_this_minute < enemies_per_minute
         {
-            self.spawn_enemy(world, config);
+            self.spawn_enemy(world, &size_weights);
             self.time_since_last_spawn = Duration::from_secs(0);
             self.enemies_spawned_this_minute += 1;
         }
 
-        if
-            config.boss_spawn_minutes.contains(&self.current_minute) &&
-            self.enemies_spawned_this_minute == 0
-        {
+        if boss_minute && self.enemies_spawned_this_minute == 0 {
             self.spawn_boss(world);
         }
     }
 
-    fn spawn_enemy(&self, world: &mut World, config: &SpawnConfig) {
-        let size = self.get_random_size(&config.size_weights);
-        let position = self.get_random_position_ground_enemy();
-        let velocity = self.get_random_velocity();
-        let health = self.get_health_based_on_size(size);
+    fn spawn_enemy(&mut self, world: &mut World, size_weights: &[f32; 4]) {
+        let max_xyz = world.world_layout.max_xyz();
+        let rng = &mut world.rng.spawning;
+        let size = Self::get_random_size(rng, size_weights);
+        let position = Self::get_random_position_ground_enemy(rng, max_xyz);
+        Self::spawn_at(world, position, size);
+    }
+    fn spawn_at(world: &mut World, position: ChunkVec3, size: PossibleEnemySizes) {
+        let velocity = Self::get_random_velocity(&mut world.rng.spawning);
+        let health = Enemies::get_health_from_size(size);
         let enemy_index = world.enemies.new_enemy(
             position,
             velocity,
@@ -154,30 +252,15 @@ impl SpawningSystem {
             EnemyType::Regular
         );
 
-        // Place the enemy in the world layout
-        let chunk_pos = position.0;
-        world.world_layout[chunk_pos.x as usize][chunk_pos.y as usize][chunk_pos.z as usize].push(
+        // Place the enemy in every tile its hitbox touches, like the movement system does
+        world.world_layout.insert_aabb(
+            position,
+            Enemies::get_hitbox_from_size(size) * 0.5,
             EntityType::Enemy(enemy_index)
         );
     }
-    fn get_health_based_on_size(&self, size: PossibleEnemySizes) -> u8 {
-        match size {
-            PossibleEnemySizes::SMALL => {
-                return 1;
-            }
-            PossibleEnemySizes::BOSS => {
-                return 10;
-            }
-            PossibleEnemySizes::LARGE => {
-                return 5;
-            }
-            PossibleEnemySizes::MEDIUM => {
-                return 3;
-            }
-        }
-    }
-    fn get_random_size(&self, weights: &[f32; 4]) -> PossibleEnemySizes {
-        let random_value: f32 = rand::gen_range(0.0, 1.0);
+    fn get_random_size(rng: &mut Rng, weights: &[f32; 4]) -> PossibleEnemySizes {
+        let random_value: f32 = rng.gen_f32();
 
         let mut cumulative_weight = 0.0;
 
@@ -196,34 +279,39 @@ impl SpawningSystem {
         PossibleEnemySizes::SMALL // Default case
     }
 
-    fn get_random_position_ground_enemy(&self) -> ChunkVec3 {
+    fn get_random_position_ground_enemy(rng: &mut Rng, max_xyz: Vec3) -> ChunkVec3 {
         let border_threshold = WORLD_BORDER + 2.0; // Distance from the border where enemies can spawn
-        let x = if rand::gen_range(0.0, 1.0) > 0.5 {
-            rand::gen_range(0.0, border_threshold)
+        let x = if rng.gen_f32() > 0.5 {
+            rng.gen_range_f32(0.0, border_threshold)
         } else {
-            rand::gen_range((CHUNK_SIZE as f32) - border_threshold, CHUNK_SIZE as f32 - WORLD_BORDER)
+            rng.gen_range_f32(max_xyz.x - border_threshold, max_xyz.x - WORLD_BORDER)
         };
-        let z = if rand::gen_range(0.0, 1.0) > 0.5 {
-            rand::gen_range(0.0, border_threshold)
+        let z = if rng.gen_f32() > 0.5 {
+            rng.gen_range_f32(0.0, border_threshold)
         } else {
-            rand::gen_range((CHUNK_SIZE as f32) - border_threshold, CHUNK_SIZE as f32 - WORLD_BORDER)
+            rng.gen_range_f32(max_xyz.z - border_threshold, max_xyz.z - WORLD_BORDER)
         };
-    
-        ChunkVec3(
-            Vec3::new(x, 8.0, z)
-        )
+
+        ChunkVec3(Vec3::new(x, 8.0, z))
     }
-    
 
-    fn get_random_velocity(&self) -> Vec3 {
+
+    /// above the room, enemies drop in from the ceiling
+    fn get_random_position_in_room(rng: &mut Rng, min: ChunkPos, max: ChunkPos) -> ChunkVec3 {
+        let x = rng.gen_range_f32(min.x as f32, max.x as f32);
+        let z = rng.gen_range_f32(min.z as f32, max.z as f32);
+        ChunkVec3(Vec3::new(x, 12.0, z))
+    }
+
+    fn get_random_velocity(rng: &mut Rng) -> Vec3 {
         // Implement logic to get a random velocity
-        Vec3::new(rand::gen_range(-1.0, 1.0), 0.0, rand::gen_range(-1.0, 1.0))
+        Vec3::new(rng.gen_range_f32(-1.0, 1.0), 0.0, rng.gen_range_f32(-1.0, 1.0))
     }
 
-    fn spawn_boss(&self, world: &mut World) {
-        let position = self.get_random_position_ground_enemy();
-        let velocity = self.get_random_velocity();
-        let health = self.get_health_based_on_size(PossibleEnemySizes::BOSS);
+    fn spawn_boss(&mut self, world: &mut World) {
+        let position = Self::get_random_position_ground_enemy(&mut world.rng.spawning, world.world_layout.max_xyz());
+        let velocity = Self::get_random_velocity(&mut world.rng.spawning);
+        let health = Enemies::get_health_from_size(PossibleEnemySizes::BOSS);
         let boss_index = world.enemies.new_enemy(
             position,
             velocity,
@@ -232,8 +320,9 @@ impl SpawningSystem {
             EnemyType::Regular
         );
 
-        let chunk_pos = position.0;
-        world.world_layout[chunk_pos.x as usize][chunk_pos.y as usize][chunk_pos.z as usize].push(
+        world.world_layout.insert_aabb(
+            position,
+            Enemies::get_hitbox_from_size(PossibleEnemySizes::BOSS) * 0.5,
             EntityType::Enemy(boss_index)
         );
     }
@@ -0,0 +1,282 @@
+use std::{ collections::HashSet, fs, path::Path };
+
+use dot_vox::{ load, DotVoxData, SceneNode };
+use shared::{
+    config::{ CHUNK_SIZE, TRAMPOLINE_DEFAULT_HEIGHT },
+    types::{
+        BlockRegistry,
+        BlockTypeId,
+        ChunkCoord,
+        ChunkPos,
+        EnemyType,
+        InteractableBlockType,
+        PossibleEnemySizes,
+    },
+    Vec3,
+};
+
+use crate::level::{
+    check_portal_links,
+    check_size,
+    enemy_spawn_tiles,
+    parse_args,
+    parse_block_type,
+    parse_enemy_size,
+    parse_enemy_type,
+    parse_facing,
+    player_start_tiles,
+    EnemySpawn,
+    Level,
+    LevelError,
+    LevelMeta,
+};
+
+// Palette files map MagicaVoxel color indices (1-255, as shown in the editor) to blocks:
+//
+//   default <entry>                  used for every index that is not listed, `solid` if omitted
+//   <index> <entry>
+//
+// where <entry> is one of
+//   empty | solid | block <block type> | trampoline [height] | portal <link> <side>
+//   | player | enemy <type> <size>
+// `solid` is short for `block stone`
+
+const HEADROOM: i32 = 4; // free tiles above the highest voxel, so spawned enemies have room to fall in
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaletteEntry {
+    Empty,
+    Block(BlockTypeId),
+    Interactable(InteractableBlockType),
+    PlayerStart,
+    Enemy(EnemyType, PossibleEnemySizes),
+}
+
+pub struct VoxPalette {
+    pub entries: [PaletteEntry; 256],
+}
+
+impl Default for VoxPalette {
+    fn default() -> Self {
+        VoxPalette {
+            entries: [PaletteEntry::Block(BlockTypeId::STONE); 256],
+        }
+    }
+}
+
+impl VoxPalette {
+    pub fn load<P: AsRef<Path>>(path: P, registry: &BlockRegistry) -> Result<VoxPalette, LevelError> {
+        let source = fs::read_to_string(path)?;
+        VoxPalette::parse(&source, registry)
+    }
+
+    pub fn parse(source: &str, registry: &BlockRegistry) -> Result<VoxPalette, LevelError> {
+        let mut default = PaletteEntry::Block(BlockTypeId::STONE);
+        let mut listed: Vec<(u8, PaletteEntry)> = Vec::new();
+        for (index, raw_line) in source.lines().enumerate() {
+            let line = index + 1;
+            let content = raw_line.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = content.split_whitespace().collect();
+            let entry = Self::parse_entry(line, &words[1..], registry)?;
+            if words[0] == "default" {
+                default = entry;
+                continue;
+            }
+            match words[0].parse::<u8>() {
+                Ok(color_index) if color_index > 0 => listed.push((color_index, entry)),
+                _ => {
+                    return Err(LevelError::Syntax {
+                        line,
+                        message: format!("`{}` is not a palette index between 1 and 255", words[0]),
+                    });
+                }
+            }
+        }
+        let mut palette = VoxPalette { entries: [default; 256] };
+        for (color_index, entry) in listed {
+            palette.entries[color_index as usize] = entry;
+        }
+        Ok(palette)
+    }
+
+    fn parse_entry(line: usize, words: &[&str], registry: &BlockRegistry) -> Result<PaletteEntry, LevelError> {
+        match words {
+            ["empty"] => Ok(PaletteEntry::Empty),
+            ["solid"] => Ok(PaletteEntry::Block(BlockTypeId::STONE)),
+            ["block", name] => Ok(PaletteEntry::Block(parse_block_type(line, name, registry)?)),
+            ["trampoline"] =>
+                Ok(PaletteEntry::Interactable(InteractableBlockType::Trampoline(TRAMPOLINE_DEFAULT_HEIGHT))),
+            ["trampoline", height] => {
+                let height = parse_args::<f32>(line, &[height], 1)?[0];
+                Ok(PaletteEntry::Interactable(InteractableBlockType::Trampoline(height)))
+            }
+            ["portal", link, facing] => {
+                let link = parse_args::<u8>(line, &[link], 1)?[0];
+                let facing = parse_facing(line, facing)?;
+                Ok(PaletteEntry::Interactable(InteractableBlockType::Portal { link, facing }))
+            }
+            ["player"] => Ok(PaletteEntry::PlayerStart),
+            ["enemy", e_type, size] =>
+                Ok(PaletteEntry::Enemy(parse_enemy_type(line, e_type)?, parse_enemy_size(line, size)?)),
+            _ =>
+                Err(LevelError::Syntax {
+                    line,
+                    message: format!("unknown palette entry `{}`", words.join(" ")),
+                }),
+        }
+    }
+}
+
+/// world space offset of every model in the scene graph, MagicaVoxel positions models by their center
+fn collect_models(data: &DotVoxData, node: u32, offset: ChunkPos, res: &mut Vec<(usize, ChunkPos)>) {
+    let Some(scene_node) = data.scenes.get(node as usize) else {
+        return;
+    };
+    match scene_node {
+        SceneNode::Transform { frames, child, .. } => {
+            let translation = frames
+                .first()
+                .and_then(|frame| frame.position())
+                .map(|p| ChunkPos::new(p.x, p.y, p.z))
+                .unwrap_or(ChunkPos::new(0, 0, 0));
+            let offset = ChunkPos::new(
+                offset.x + translation.x,
+                offset.y + translation.y,
+                offset.z + translation.z
+            );
+            collect_models(data, *child, offset, res);
+        }
+        SceneNode::Group { children, .. } => {
+            for child in children {
+                collect_models(data, *child, offset, res);
+            }
+        }
+        SceneNode::Shape { models, .. } => {
+            for model in models {
+                let model_id = model.model_id as usize;
+                let Some(size) = data.models.get(model_id).map(|m| m.size) else {
+                    continue;
+                };
+                res.push((
+                    model_id,
+                    ChunkPos::new(
+                        offset.x - (size.x as i32) / 2,
+                        offset.y - (size.y as i32) / 2,
+                        offset.z - (size.z as i32) / 2
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+impl Level {
+    /// Builds a level out of every model in a .vox scene, the world gets as many chunks as the scene needs
+    pub fn from_vox(path: &str, palette: &VoxPalette) -> Result<Level, LevelError> {
+        let data = load(path).map_err(|err| LevelError::Vox(err.to_owned()))?;
+
+        let mut placements = Vec::new();
+        if data.scenes.is_empty() {
+            placements.extend((0..data.models.len()).map(|id| (id, ChunkPos::new(0, 0, 0))));
+        } else {
+            collect_models(&data, 0, ChunkPos::new(0, 0, 0), &mut placements);
+        }
+
+        // MagicaVoxel is z up, the game is y up
+        let mut voxels = Vec::new();
+        for (model_id, offset) in placements {
+            for voxel in &data.models[model_id].voxels {
+                let entry = palette.entries[(voxel.i as usize) + 1];
+                if entry == PaletteEntry::Empty {
+                    continue;
+                }
+                let pos = ChunkPos::new(
+                    offset.x + (voxel.x as i32),
+                    offset.z + (voxel.z as i32),
+                    offset.y + (voxel.y as i32)
+                );
+                voxels.push((pos, entry));
+            }
+        }
+        if voxels.is_empty() {
+            return Err(LevelError::Vox("scene does not contain any voxels".to_owned()));
+        }
+
+        let min = voxels.iter().fold(ChunkPos::new(i32::MAX, i32::MAX, i32::MAX), |acc, (p, _)| {
+            ChunkPos::new(acc.x.min(p.x), acc.y.min(p.y), acc.z.min(p.z))
+        });
+        let max = voxels.iter().fold(ChunkPos::new(i32::MIN, i32::MIN, i32::MIN), |acc, (p, _)| {
+            ChunkPos::new(acc.x.max(p.x), acc.y.max(p.y), acc.z.max(p.z))
+        });
+        const SIZE: i32 = CHUNK_SIZE as i32;
+        let size = ChunkCoord::new(
+            (max.x - min.x) / SIZE + 1,
+            (max.y - min.y + HEADROOM) / SIZE + 1,
+            (max.z - min.z) / SIZE + 1
+        );
+        check_size(size)?;
+
+        let mut player_start = None;
+        let mut solid_blocks = Vec::new();
+        let mut interactable_blocks = Vec::new();
+        let mut enemy_spawns = Vec::new();
+        for (pos, entry) in voxels {
+            let pos = ChunkPos::new(pos.x - min.x, pos.y - min.y, pos.z - min.z);
+            match entry {
+                PaletteEntry::Empty => {}
+                PaletteEntry::Block(b_type) => solid_blocks.push((pos, b_type)),
+                PaletteEntry::Interactable(b_type) => interactable_blocks.push((pos, b_type)),
+                PaletteEntry::PlayerStart => {
+                    if player_start.is_some() {
+                        return Err(LevelError::Vox("scene contains more than one player start".to_owned()));
+                    }
+                    player_start = Some(pos.as_vec3());
+                }
+                PaletteEntry::Enemy(e_type, e_size) => {
+                    enemy_spawns.push(EnemySpawn { pos: pos.as_vec3(), size: e_size, e_type });
+                }
+            }
+        }
+        let player_start: Vec3 = player_start.ok_or_else(|| {
+            LevelError::Vox("scene has no voxel mapped to the player start".to_owned())
+        })?;
+        check_portal_links(&interactable_blocks)?;
+
+        // models may overlap in the scene, the .lvl checks apply without line numbers
+        let mut occupied = HashSet::new();
+        let blocks = solid_blocks.iter().map(|(pos, _)| *pos).chain(interactable_blocks.iter().map(|(pos, _)| *pos));
+        for pos in blocks {
+            if !occupied.insert(pos) {
+                return Err(LevelError::Vox(format!("models overlap at {} {} {}", pos.x, pos.y, pos.z)));
+            }
+        }
+        // HEADROOM keeps the hitbox above the start inside the world
+        if let Some(tile) = player_start_tiles(player_start).find(|tile| occupied.contains(tile)) {
+            return Err(LevelError::Vox(format!("player start overlaps a block at {} {} {}", tile.x, tile.y, tile.z)));
+        }
+        for spawn in &enemy_spawns {
+            if let Some(tile) = enemy_spawn_tiles(spawn).find(|tile| occupied.contains(tile)) {
+                return Err(
+                    LevelError::Vox(format!("enemy spawn overlaps a block at {} {} {}", tile.x, tile.y, tile.z))
+                );
+            }
+        }
+
+        let name = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Level {
+            meta: LevelMeta { name, author: String::new(), seed: None, floor: 0 },
+            size,
+            player_start,
+            solid_blocks,
+            interactable_blocks,
+            enemy_spawns,
+            rooms: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,410 @@
+use std::{ collections::VecDeque, time::Duration };
+use macroquad::math::{ vec3, Vec3 };
+use shared::{
+    config::{ BOSS_EXPLOSION_RADIUS, ENEMY_CONTACT_REACH, ENEMY_CONTACT_SHOVE, JUMP_BUFFER_TIME, PHYSICS_FRAME_TIME, WORLD_UP },
+    types::{
+        BlockRegistry,
+        BlockTypeId,
+        ChunkVec3,
+        Enemies,
+        EnemyHandle,
+        EnemyType,
+        EntityType,
+        InteractableBlockType,
+        InteractableBlocks,
+        LayoutIssue,
+        MoveInput,
+        Player,
+        PossibleEnemySizes,
+        Rng,
+        RunRng,
+        SolidBlocks,
+        TickInput,
+        WorldEvent,
+        WorldLayout,
+    },
+};
+
+use crate::{
+    floor::{ FloorGenerator, Room, RoomState },
+    level::Level,
+    movement::MovementSystem,
+    shooting::shoot,
+    spawning::{ update_spawning_system, SpawningSystem },
+    util::{ time_seed, vec3_no_y },
+};
+
+const STAIRS_REACH: f32 = 0.1; // how close the player has to walk up to the stairs
+
+/// the whole simulation of a run. It only moves forward through `update` with the input of one physics step,
+/// so it runs the same without a window, see `TickInput`
+pub struct World {
+    pub player: Player,
+    pub enemies: Enemies,
+    pub solid_blocks: SolidBlocks,
+    pub interactable_blocks: InteractableBlocks,
+    pub world_layout: WorldLayout,
+    pub world_event_queue: VecDeque<WorldEvent>,
+    pub block_registry: BlockRegistry,
+    pub level: Level,
+    pub rooms: Vec<Room>,
+    pub rng: RunRng,
+}
+
+impl World {
+    pub fn from_level(level: &Level) -> Self {
+        let mut world = World {
+            player: Player::default(),
+            world_layout: WorldLayout::new(level.size),
+            enemies: Enemies::new(),
+            solid_blocks: SolidBlocks::new(), // make static
+            interactable_blocks: InteractableBlocks::new(),
+            world_event_queue: VecDeque::new(),
+            block_registry: BlockRegistry::default(),
+            level: level.clone(),
+            rooms: level.rooms.clone(),
+            rng: RunRng::new(level.meta.seed.unwrap_or_else(time_seed)),
+        };
+        world.player.body.pos = Player::standing_in(level.player_start);
+        world.player.body.prev_pos = world.player.body.pos;
+        world.world_layout.insert_aabb(world.player.body.pos, world.player.body.half_extents, EntityType::Player);
+
+        for &(pos, b_type) in &level.solid_blocks {
+            world.world_layout.set_block(pos, b_type, &world.block_registry);
+            let hit_points = world.block_registry.get(b_type).hit_points;
+            world.solid_blocks.new_block(ChunkVec3(pos.as_vec3()), b_type, hit_points);
+        }
+        for &(pos, b_type) in &level.interactable_blocks {
+            let index = world.interactable_blocks.new_block(ChunkVec3(pos.as_vec3()), b_type);
+            world.world_layout.insert(pos, EntityType::InteractableBlock(index));
+        }
+        world.interactable_blocks.link_portals();
+        for spawn in &level.enemy_spawns {
+            let position = ChunkVec3(spawn.pos);
+            let handle = world.enemies.new_enemy(
+                position,
+                Vec3::ZERO,
+                spawn.size,
+                Enemies::get_health_from_size(spawn.size),
+                spawn.e_type
+            );
+            world.world_layout.insert_aabb(
+                position,
+                Enemies::get_hitbox_from_size(spawn.size) * 0.5,
+                EntityType::Enemy(handle)
+            );
+        }
+        world
+    }
+
+    /// the run stands still on the game over screen until the player jumps to start over
+    pub fn is_game_over(&self) -> bool {
+        self.player.health <= 0.0
+    }
+
+    /// see `WorldLayout::check_consistency`
+    pub fn check_consistency(&self) -> Vec<LayoutIssue> {
+        self.world_layout.check_consistency(
+            &self.player,
+            &self.enemies,
+            &self.solid_blocks,
+            &self.interactable_blocks,
+            &self.block_registry
+        )
+    }
+
+    fn remove_enemy(&mut self, h: EnemyHandle) {
+        let index = h.0 as usize;
+        if index < self.enemies.bodies.len() {
+            let body = self.enemies.bodies[index];
+            let occupied_tiles = Enemies::get_occupied_tiles(&body.pos, &body.half_extents);
+            for tile in occupied_tiles {
+                self.world_layout.remove(tile, EntityType::Enemy(h));
+            }
+            self.enemies.remove_enemy(h);
+        }
+    }
+
+    fn handle_world_events(&mut self) {
+        while let Some(event) = self.world_event_queue.pop_front() {
+            match event {
+                WorldEvent::KillEnemy(h) => {
+                    let index = h.0 as usize;
+                    let alive = index < self.enemies.bodies.len() && self.enemies.e_type[index] != EnemyType::Empty;
+                    if alive && self.enemies.size[index] == PossibleEnemySizes::BOSS {
+                        let center = self.enemies.bodies[index].pos;
+                        self.world_event_queue.push_back(WorldEvent::Explosion(center, BOSS_EXPLOSION_RADIUS));
+                    }
+                    self.remove_enemy(h);
+                }
+                WorldEvent::HitEnemy(h) => {
+                    let index = h.0 as usize;
+                    if index < self.enemies.healths.len() {
+                        if self.enemies.healths[index] > 1 {
+                            self.enemies.healths[index] -= 1;
+                        } else {
+                            self.world_event_queue.push_back(WorldEvent::KillEnemy(h));
+                        }
+                    }
+                }
+                WorldEvent::DamagePlayer(amount) => {
+                    self.player.health = (self.player.health - amount).max(0.0);
+                }
+                WorldEvent::BlockDamaged(pos) => {
+                    let Some(index) = self.solid_blocks.index_of(pos) else {
+                        continue;
+                    };
+                    if !self.block_registry.get(self.solid_blocks.b_types[index]).destructible {
+                        continue;
+                    }
+                    if self.solid_blocks.hit_points[index] > 1 {
+                        self.solid_blocks.hit_points[index] -= 1;
+                    } else {
+                        self.world_event_queue.push_back(WorldEvent::BlockDestroyed(pos));
+                    }
+                }
+                WorldEvent::BlockDestroyed(pos) => {
+                    self.world_layout.clear_block(pos);
+                    if let Some(index) = self.solid_blocks.index_of(pos) {
+                        self.solid_blocks.remove_block(index);
+                    }
+                }
+                WorldEvent::Explosion(center, radius) => {
+                    let min = center.0 - Vec3::splat(radius);
+                    let max = center.0 + Vec3::splat(radius);
+                    for tile in WorldLayout::tiles_in_aabb(min, max) {
+                        if
+                            tile.as_vec3().distance(center.0) <= radius &&
+                            self.world_layout.block_at(tile).is_some()
+                        {
+                            self.world_event_queue.push_back(WorldEvent::BlockDamaged(tile));
+                        }
+                    }
+                    for entity in self.world_layout.entities_in_aabb(min, max) {
+                        let EntityType::Enemy(h) = entity else {
+                            continue;
+                        };
+                        // the spatial hash hands out everything in the box, only the sphere gets hit
+                        if let Some(body) = self.enemies.bodies.get(h.0 as usize) {
+                            if body.pos.0.distance(center.0) <= radius {
+                                self.world_event_queue.push_back(WorldEvent::HitEnemy(h));
+                            }
+                        }
+                    }
+                }
+                // only changes velocities, the movement system moves the entity and resolves collisions
+                WorldEvent::Impulse(entity, impulse) => {
+                    match entity {
+                        EntityType::Player => {
+                            self.player.body.vel += impulse / self.player.body.mass;
+                        }
+                        EntityType::Enemy(h) => {
+                            let index = h.0 as usize;
+                            if index >= self.enemies.bodies.len() || self.enemies.e_type[index] == EnemyType::Empty {
+                                continue;
+                            }
+                            let body = &mut self.enemies.bodies[index];
+                            body.vel += impulse / body.mass;
+                        }
+                        EntityType::InteractableBlock(_) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// swaps in a fresh world for the given level, the player only keeps what the run carries over
+    pub fn load_level(&mut self, level: &Level) {
+        *self = World::from_level(level);
+    }
+
+    /// floor runs start over on a new first floor, handmade arenas start over as they are. The new run seed
+    /// follows from the old one, so a recorded session replays the same through every death
+    fn restart(&mut self, spawner: &mut SpawningSystem) {
+        let seed = Rng::new(self.rng.seed).next_u64();
+        let level = if self.rooms.is_empty() {
+            let mut level = self.level.clone();
+            level.meta.seed = Some(seed);
+            level
+        } else {
+            FloorGenerator::generate(seed, 1)
+        };
+        self.load_level(&level);
+        *spawner = SpawningSystem::new();
+    }
+
+    /// the run rng goes on with the next floor like the health does
+    fn next_floor(&mut self, spawner: &mut SpawningSystem) {
+        let level = FloorGenerator::generate(self.rng.seed, self.level.meta.floor + 1);
+        let (health, rng) = (self.player.health, self.rng);
+        self.load_level(&level);
+        self.player.health = health;
+        self.rng = rng;
+        *spawner = SpawningSystem::new();
+    }
+
+    fn set_doors(&mut self, room: usize, locked: bool) {
+        for i in 0..self.rooms[room].doors.len() {
+            let door = self.rooms[room].doors[i];
+            let b_type = self.world_layout.block_at(door);
+            if locked && b_type.is_none() {
+                self.world_layout.set_block(door, BlockTypeId::DOOR, &self.block_registry);
+                self.solid_blocks.new_block(ChunkVec3(door.as_vec3()), BlockTypeId::DOOR, 0);
+            } else if !locked && b_type == Some(BlockTypeId::DOOR) {
+                self.world_layout.clear_block(door);
+                if let Some(index) = self.solid_blocks.index_of(door) {
+                    self.solid_blocks.remove_block(index);
+                }
+            }
+        }
+    }
+
+    /// locks rooms as soon as the player walks in and opens them once every enemy is dead,
+    /// returns true when the player took the stairs of a cleared floor
+    fn update_rooms(&mut self, spawner: &mut SpawningSystem) -> bool {
+        for i in 0..self.rooms.len() {
+            match self.rooms[i].state {
+                RoomState::Idle if self.rooms[i].encloses(self.player.body.pos, self.player.body.half_extents) => {
+                    self.rooms[i].state = RoomState::Locked;
+                    self.set_doors(i, true);
+                    let profile = spawner.room_profile(self.level.meta.floor, i, self.rooms[i].kind);
+                    spawner.start_room(profile, self.rooms[i].min, self.rooms[i].max);
+                }
+                RoomState::Locked => {
+                    let enemies_left = self.enemies.e_type.iter().any(|e_type| *e_type != EnemyType::Empty);
+                    if spawner.room_finished() && !enemies_left {
+                        self.rooms[i].state = RoomState::Cleared;
+                        self.set_doors(i, false);
+                        spawner.active_room = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let floor_cleared = self.rooms.iter().all(|room| room.state == RoomState::Cleared);
+        let reach = self.player.body.half_extents + STAIRS_REACH;
+        floor_cleared &&
+            self.world_layout
+                .entities_in_aabb(self.player.body.pos.0 - reach, self.player.body.pos.0 + reach)
+                .iter()
+                .any(|entity| {
+                    matches!(entity, EntityType::InteractableBlock(index)
+                        if self.interactable_blocks.b_types[*index as usize] == InteractableBlockType::Stairs)
+                })
+    }
+
+    /// one physics step, PHYSICS_FRAME_TIME long
+    pub fn update(&mut self, spawner: &mut SpawningSystem, input: &TickInput) {
+        if self.is_game_over() {
+            if input.jump_pressed {
+                self.restart(spawner);
+            }
+            // nothing moves anymore, rendering must not keep blending in the last step
+            self.player.body.prev_pos = self.player.body.pos;
+            for body in &mut self.enemies.bodies {
+                body.prev_pos = body.pos;
+            }
+            return;
+        }
+        self.apply_input(input);
+        self.handle_world_events();
+        if self.is_game_over() {
+            return;
+        }
+        if !self.rooms.is_empty() && self.update_rooms(spawner) {
+            self.next_floor(spawner);
+            return;
+        }
+        let ground_block = MovementSystem::update_player(
+            &mut self.player,
+            &self.enemies,
+            &self.interactable_blocks,
+            &mut self.world_layout,
+            &self.block_registry
+        );
+        if let Some(ground_block) = ground_block {
+            let damage = self.block_registry.get(ground_block).damage_on_touch;
+            if damage > 0.0 {
+                self.world_event_queue.push_back(WorldEvent::DamagePlayer(damage * PHYSICS_FRAME_TIME));
+            }
+        }
+        MovementSystem::update_enemies(
+            &self.player.body,
+            &mut self.enemies,
+            &self.interactable_blocks,
+            &mut self.world_layout,
+            &self.block_registry
+        );
+        if !self.player.dash.is_invulnerable() {
+            let mut contact_damage = 0.0;
+            for h in self.touching_enemies() {
+                let index = h.0 as usize;
+                let body = &self.enemies.bodies[index];
+                contact_damage += Enemies::get_contact_damage_from_size(self.enemies.size[index]);
+                let away = vec3_no_y(self.player.body.pos.0 - body.pos.0).normalize_or_zero();
+                let shove = away * body.mass * ENEMY_CONTACT_SHOVE * PHYSICS_FRAME_TIME;
+                self.world_event_queue.push_back(WorldEvent::Impulse(EntityType::Player, shove));
+            }
+            if contact_damage > 0.0 {
+                self.world_event_queue.push_back(WorldEvent::DamagePlayer(contact_damage * PHYSICS_FRAME_TIME));
+            }
+        }
+        update_spawning_system(self, spawner, Duration::from_secs_f32(PHYSICS_FRAME_TIME));
+        debug_assert!(
+            self.world_layout.entities_at(self.player.body.pos.to_chunk()).contains(&EntityType::Player)
+        );
+    }
+
+    fn apply_input(&mut self, input: &TickInput) {
+        self.player.turn(input.look);
+        if input.shoot {
+            self.world_event_queue.extend(
+                shoot(&mut self.player, &self.enemies, &self.world_layout, &self.block_registry)
+            );
+        }
+        if input.next_weapon {
+            self.player.swap_next_weapon();
+        }
+        if input.jump_pressed {
+            self.player.jump.buffered = JUMP_BUFFER_TIME;
+        }
+        self.player.jump.held = input.jump_held;
+        if input.dash {
+            self.player.dash.requested = true;
+        }
+        // velocity belongs to the movement system, it accelerates towards the wish direction
+        let forward = vec3(self.player.yaw.cos(), 0.0, self.player.yaw.sin());
+        let right = forward.cross(WORLD_UP);
+        self.player.input = MoveInput {
+            wish_dir: (forward * input.movement.y + right * input.movement.x).normalize_or_zero(),
+            sprint: input.sprint,
+            crouch: input.crouch,
+        };
+    }
+
+    /// enemies close enough to hurt and shove the player
+    fn touching_enemies(&self) -> Vec<EnemyHandle> {
+        let half_hitbox = self.player.body.half_extents;
+        let reach = half_hitbox + ENEMY_CONTACT_REACH;
+        let pos = self.player.body.pos.0;
+        self.world_layout
+            .entities_in_aabb(pos - reach, pos + reach)
+            .iter()
+            .filter_map(|entity| {
+                let EntityType::Enemy(h) = entity else {
+                    return None;
+                };
+                let index = h.0 as usize;
+                if self.enemies.e_type[index] == EnemyType::Empty {
+                    return None;
+                }
+                let body = &self.enemies.bodies[index];
+                let offset = (body.pos.0 - pos).abs();
+                let touching = offset.cmple(reach + body.half_extents).all();
+                touching.then_some(*h)
+            })
+            .collect()
+    }
+
+}
@@ -0,0 +1,85 @@
+//! generated arenas over a range of seeds, the player can walk back to the start from every interior column
+
+use std::collections::VecDeque;
+
+use anabolic::{ arena::{ ArenaConfig, ArenaGenerator }, level::Level };
+use shared::{ config::CHUNK_SIZE, types::ChunkCoord };
+
+/// walks the finished level backwards from the start, the player drops down any height
+/// but climbs a single tile at a time. Pillars are fine, pits nobody can climb out of are not
+fn stuck_columns(level: &Level) -> Vec<(i32, i32)> {
+    let width = level.size.x * (CHUNK_SIZE as i32);
+    let depth = level.size.z * (CHUNK_SIZE as i32);
+    let index = |x: i32, z: i32| (x * depth + z) as usize;
+    let mut heights = vec![0; (width * depth) as usize];
+    for (pos, _) in &level.solid_blocks {
+        heights[index(pos.x, pos.z)] = heights[index(pos.x, pos.z)].max(pos.y);
+    }
+
+    let interior = |x: i32, z: i32| x >= 1 && z >= 1 && x < width - 1 && z < depth - 1;
+    let start = (level.player_start.x as i32, level.player_start.z as i32);
+    let mut reached = vec![false; heights.len()];
+    reached[index(start.0, start.1)] = true;
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, z)) = queue.pop_front() {
+        let height = heights[index(x, z)];
+        for (nx, nz) in [(x - 1, z), (x + 1, z), (x, z - 1), (x, z + 1)] {
+            if interior(nx, nz) && !reached[index(nx, nz)] && height - heights[index(nx, nz)] <= 1 {
+                reached[index(nx, nz)] = true;
+                queue.push_back((nx, nz));
+            }
+        }
+    }
+
+    (1..width - 1)
+        .flat_map(|x| (1..depth - 1).map(move |z| (x, z)))
+        .filter(|&(x, z)| !reached[index(x, z)])
+        .collect()
+}
+
+#[test]
+fn open_arenas_have_no_pits() {
+    let config = ArenaConfig {
+        size: ChunkCoord::new(3, 1, 3),
+        pillars: 14,
+        platforms: 4,
+        cover: 12,
+        flat_areas: Vec::new(),
+    };
+    for seed in 0..40 {
+        let level = ArenaGenerator::generate(seed, &config);
+        assert_eq!(level.meta.seed, Some(seed));
+        assert_eq!(stuck_columns(&level), Vec::new(), "seed {}", seed);
+    }
+}
+
+#[test]
+fn crowded_rooms_have_no_pits() {
+    // a floor room packed with more than the floor generator ever places
+    let config = ArenaConfig {
+        size: ChunkCoord::new(1, 1, 1),
+        pillars: 8,
+        platforms: 3,
+        cover: 8,
+        flat_areas: vec![[1, 6, 14, 7]],
+    };
+    for seed in 0..200 {
+        let level = ArenaGenerator::generate(seed, &config);
+        assert_eq!(stuck_columns(&level), Vec::new(), "seed {}", seed);
+    }
+}
+
+#[test]
+fn same_seed_builds_the_same_arena() {
+    let config = ArenaConfig {
+        size: ChunkCoord::new(2, 1, 2),
+        pillars: 6,
+        platforms: 2,
+        cover: 6,
+        flat_areas: Vec::new(),
+    };
+    let first = ArenaGenerator::generate(9, &config);
+    let second = ArenaGenerator::generate(9, &config);
+    assert_eq!(first.solid_blocks, second.solid_blocks);
+    assert_eq!(first.player_start, second.player_start);
+}
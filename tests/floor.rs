@@ -0,0 +1,71 @@
+//! generated floors over a range of seeds and floor numbers
+
+use std::collections::HashSet;
+
+use anabolic::{ floor::{ FloorGenerator, RoomKind }, level::Level, World };
+use shared::types::{ ChunkPos, InteractableBlockType };
+
+fn floors() -> impl Iterator<Item = (u64, u32, Level)> {
+    (0..8u64).flat_map(|seed| (1..=5).map(move |floor| (seed, floor, FloorGenerator::generate(seed, floor))))
+}
+
+#[test]
+fn generated_floors_are_consistent() {
+    for (seed, floor, level) in floors() {
+        let issues = World::from_level(&level).check_consistency();
+        assert!(issues.is_empty(), "seed {} floor {}: {:?}", seed, floor, issues);
+    }
+}
+
+#[test]
+fn doors_line_up_between_neighbouring_rooms() {
+    for (seed, floor, level) in floors() {
+        let solid: HashSet<ChunkPos> = level.solid_blocks.iter().map(|(pos, _)| *pos).collect();
+        let rooms = &level.rooms;
+        assert!(rooms.first().unwrap().doors.iter().all(|door| door.x > rooms[0].max.x));
+        assert!(rooms.last().unwrap().doors.iter().all(|door| door.x < rooms[rooms.len() - 1].min.x));
+
+        for pair in rooms.windows(2) {
+            let east: HashSet<ChunkPos> = pair[0].doors.iter().filter(|door| door.x > pair[0].max.x).copied().collect();
+            let west: HashSet<ChunkPos> = pair[1].doors.iter().filter(|door| door.x < pair[1].min.x).copied().collect();
+            assert!(!east.is_empty(), "seed {} floor {}: room without a way on", seed, floor);
+            assert_eq!(east, west, "seed {} floor {}", seed, floor);
+            for door in &east {
+                assert!(!solid.contains(door), "seed {} floor {}: doorway {:?} is walled up", seed, floor, door);
+                let below = ChunkPos::new(door.x, door.y - 1, door.z);
+                let floored = door.y > 1 || solid.contains(&below);
+                assert!(floored, "seed {} floor {}: no floor under {:?}", seed, floor, door);
+            }
+        }
+    }
+}
+
+#[test]
+fn boss_room_ends_the_floor_with_stairs() {
+    for (seed, floor, level) in floors() {
+        let kinds: Vec<RoomKind> = level.rooms.iter().map(|room| room.kind).collect();
+        assert_eq!(kinds.first(), Some(&RoomKind::Start));
+        assert_eq!(kinds.iter().filter(|&&kind| kind == RoomKind::Boss).count(), 1);
+        assert_eq!(kinds.last(), Some(&RoomKind::Boss));
+
+        let boss = level.rooms.last().unwrap();
+        let stairs: Vec<ChunkPos> = level.interactable_blocks
+            .iter()
+            .filter(|(_, b_type)| *b_type == InteractableBlockType::Stairs)
+            .map(|(pos, _)| *pos)
+            .collect();
+        assert_eq!(stairs.len(), 1, "seed {} floor {}", seed, floor);
+        let tile = stairs[0];
+        let inside = tile.x >= boss.min.x && tile.x <= boss.max.x && tile.z >= boss.min.z && tile.z <= boss.max.z;
+        assert!(inside, "seed {} floor {}: stairs at {:?} outside of the boss room", seed, floor, tile);
+    }
+}
+
+#[test]
+fn same_seed_and_floor_build_the_same_floor() {
+    let first = FloorGenerator::generate(5, 2);
+    let second = FloorGenerator::generate(5, 2);
+    assert_eq!(first.solid_blocks, second.solid_blocks);
+    assert_eq!(first.interactable_blocks, second.interactable_blocks);
+    assert_ne!(first.solid_blocks, FloorGenerator::generate(5, 3).solid_blocks);
+}
@@ -0,0 +1,65 @@
+//! steps the simulation without a window, the way tools and the replay verifier do
+
+use anabolic::{ level::Level, spawning::SpawningSystem, World };
+use shared::{ types::{ BlockRegistry, TickInput }, vec2 };
+
+const ARENA: &str = "
+name headless
+size 1 1 1
+fill 0 0 0 15 0 15
+player 8 1 8
+";
+
+fn arena() -> (World, SpawningSystem) {
+    let mut level = Level::parse(ARENA, &BlockRegistry::default()).expect("test arena should parse");
+    level.meta.seed = Some(1);
+    (World::from_level(&level), SpawningSystem::new())
+}
+
+fn step(world: &mut World, spawner: &mut SpawningSystem, input: TickInput, ticks: usize) {
+    let mut input = input;
+    for _ in 0..ticks {
+        world.update(spawner, &input);
+        input.end_tick();
+    }
+}
+
+#[test]
+fn walking_moves_the_player_along_the_floor() {
+    let (mut world, mut spawner) = arena();
+    step(&mut world, &mut spawner, TickInput::default(), 30); // settle on the floor
+    let start = world.player.body.pos.0;
+    let forward = TickInput { movement: vec2(0.0, 1.0), ..Default::default() };
+    step(&mut world, &mut spawner, forward, 30);
+    let end = world.player.body.pos.0;
+    assert!(world.player.body.grounded);
+    assert!((end.y - start.y).abs() < 0.01, "walking changed the height from {} to {}", start.y, end.y);
+    assert!(vec2(end.x - start.x, end.z - start.z).length() > 1.0, "player only got from {} to {}", start, end);
+}
+
+#[test]
+fn jumping_leaves_the_ground() {
+    let (mut world, mut spawner) = arena();
+    step(&mut world, &mut spawner, TickInput::default(), 30);
+    let start = world.player.body.pos.0.y;
+    let jump = TickInput { jump_pressed: true, jump_held: true, ..Default::default() };
+    step(&mut world, &mut spawner, jump, 10);
+    assert!(world.player.body.pos.0.y > start + 0.5);
+    assert!(!world.player.body.grounded);
+}
+
+#[test]
+fn every_input_flag_steps_without_panicking() {
+    let (mut world, mut spawner) = arena();
+    let inputs = [
+        TickInput { next_weapon: true, ..Default::default() },
+        TickInput { shoot: true, ..Default::default() },
+        TickInput { dash: true, movement: vec2(1.0, 0.0), ..Default::default() },
+        TickInput { crouch: true, sprint: true, movement: vec2(0.0, 1.0), ..Default::default() },
+        TickInput { look: vec2(0.3, -0.2), ..Default::default() },
+    ];
+    for input in inputs {
+        step(&mut world, &mut spawner, input, 5);
+    }
+    assert!(world.check_consistency().is_empty());
+}
@@ -0,0 +1,88 @@
+//! `World::check_consistency` over real runs, and on layouts broken on purpose
+
+use anabolic::{ replay::{ Replay, RunStart }, spawning::SpawningSystem, World };
+use shared::types::{ EnemyHandle, EntityType, LayoutIssue, TickInput };
+
+fn assert_consistent(world: &World, tick: usize) {
+    let issues = world.check_consistency();
+    assert!(issues.is_empty(), "tick {}: {:?}", tick, issues);
+}
+
+/// checks the layout every `every` ticks and once more at the end, a check costs far more than a tick
+fn step_checked(world: &mut World, spawner: &mut SpawningSystem, inputs: &[TickInput], every: usize) {
+    assert_consistent(world, 0);
+    for (tick, input) in inputs.iter().enumerate() {
+        world.update(spawner, input);
+        if (tick + 1) % every == 0 || tick + 1 == inputs.len() {
+            assert_consistent(world, tick + 1);
+        }
+    }
+}
+
+#[test]
+fn recorded_floor_run_stays_consistent() {
+    // walks into a combat room and clears it, so spawns, kills and doors all happen
+    let replay = Replay::load_from_file("tests/replays/floor.anrp").expect("recorded replay should load");
+    let (mut world, mut spawner) = replay.start_run().expect("recorded run should start");
+    step_checked(&mut world, &mut spawner, &replay.inputs, 25);
+}
+
+#[test]
+#[ignore = "checks all 1500 ticks, takes about half a minute, run with `cargo test -- --ignored`"]
+fn recorded_floor_run_stays_consistent_every_tick() {
+    let replay = Replay::load_from_file("tests/replays/floor.anrp").expect("recorded replay should load");
+    let (mut world, mut spawner) = replay.start_run().expect("recorded run should start");
+    step_checked(&mut world, &mut spawner, &replay.inputs, 1);
+}
+
+#[test]
+fn seeded_arena_run_stays_consistent() {
+    let start = RunStart::level("levels/default.lvl").expect("default level should load");
+    let (mut world, mut spawner) = Replay::new(start, 7).start_run().expect("arena run should start");
+    let inputs: Vec<TickInput> = (0..600)
+        .map(|tick| TickInput {
+            movement: shared::vec2(((tick / 90) % 3) as f32 - 1.0, 1.0),
+            look: shared::vec2(0.02, 0.0),
+            jump_pressed: tick % 120 == 0,
+            shoot: tick % 10 == 0,
+            ..Default::default()
+        })
+        .collect();
+    step_checked(&mut world, &mut spawner, &inputs, 1);
+}
+
+fn fresh_floor() -> World {
+    Replay::new(RunStart::Floor, 3).start_run().expect("floor run should start").0
+}
+
+#[test]
+fn reports_an_entity_missing_from_its_tile() {
+    let mut world = fresh_floor();
+    let tile = world.player.body.pos.to_chunk();
+    world.world_layout.remove(tile, EntityType::Player);
+    assert!(world.check_consistency().contains(&LayoutIssue::MissingOccupancy(tile, EntityType::Player)));
+}
+
+#[test]
+fn reports_a_stale_enemy_handle() {
+    let mut world = fresh_floor();
+    let tile = world.player.body.pos.to_chunk();
+    let stale = EntityType::Enemy(EnemyHandle(world.enemies.bodies.len() as u16));
+    world.world_layout.insert(tile, stale);
+    assert!(world.check_consistency().contains(&LayoutIssue::StaleHandle(tile, stale)));
+}
+
+#[test]
+fn reports_a_block_missing_from_its_chunk() {
+    let mut world = fresh_floor();
+    let tile = world.solid_blocks.positions[0].to_chunk();
+    world.world_layout.clear_block(tile);
+    assert!(world.check_consistency().contains(&LayoutIssue::MissingBlock(tile)));
+}
+
+#[test]
+fn reports_mismatched_enemy_arrays() {
+    let mut world = fresh_floor();
+    world.enemies.healths.push(1);
+    assert!(world.check_consistency().contains(&LayoutIssue::ArrayLengthMismatch("enemies")));
+}
@@ -0,0 +1,71 @@
+//! level file parsing, and the errors broken level files come back with
+
+use anabolic::level::{ Level, LevelError };
+use shared::types::{ BlockRegistry, ChunkPos };
+
+fn parse(body: &str) -> Result<Level, LevelError> {
+    let source = format!("name test\nsize 1 1 1\nplayer 8 1 8\nfill 0 0 0 15 0 15\n{}", body);
+    Level::parse(&source, &BlockRegistry::default())
+}
+
+#[test]
+fn default_level_parses() {
+    let level = Level::load("levels/default.lvl", &BlockRegistry::default()).expect("default level should load");
+    assert_eq!(level.meta.name, "Training Floor");
+    assert_eq!(level.enemy_spawns.len(), 2);
+}
+
+#[test]
+fn reports_syntax_errors_with_their_line() {
+    assert!(matches!(parse("wall 1 1 1"), Err(LevelError::Syntax { line: 5, .. })));
+    assert!(matches!(parse("solid 1 one 1"), Err(LevelError::Syntax { line: 5, .. })));
+    assert!(matches!(parse("enemy regular tiny 3 3 3"), Err(LevelError::Syntax { line: 5, .. })));
+    assert!(matches!(parse("solid 1 1 1 cheese"), Err(LevelError::Syntax { line: 5, .. })));
+}
+
+#[test]
+fn reports_missing_directives() {
+    let registry = BlockRegistry::default();
+    assert!(matches!(Level::parse("name test\nplayer 1 1 1", &registry), Err(LevelError::Missing("size"))));
+    assert!(matches!(Level::parse("name test\nsize 1 1 1", &registry), Err(LevelError::Missing("player"))));
+}
+
+#[test]
+fn reports_blocks_and_spawns_out_of_bounds() {
+    assert!(matches!(parse("solid 16 1 1"), Err(LevelError::OutOfBounds { line: 5, .. })));
+    assert!(matches!(parse("fill 0 1 0 0 1 99"), Err(LevelError::OutOfBounds { line: 5, .. })));
+    assert!(matches!(parse("enemy regular small 3 3 -2"), Err(LevelError::OutOfBounds { line: 5, .. })));
+}
+
+#[test]
+fn reports_overlaps() {
+    assert!(matches!(parse("solid 1 0 1"), Err(LevelError::BlockOverlap { line: 5, .. })));
+    assert!(matches!(parse("solid 8 2 8"), Err(LevelError::PlayerStartOverlap { line: 3, .. })));
+    assert!(matches!(parse("enemy regular small 3 0 3"), Err(LevelError::EnemySpawnOverlap { line: 5, .. })));
+}
+
+#[test]
+fn enemy_spawns_check_every_tile_of_their_hitbox() {
+    // the boss center sits in the open, its hitbox reaches into the wall next to it
+    let wall = "fill 5 1 0 5 3 15\n";
+    assert!(parse(&format!("{}enemy regular boss 3 2 3", wall)).is_ok());
+    let err = parse(&format!("{}enemy regular boss 3.8 2 3", wall)).expect_err("boss reaches into the wall");
+    assert!(matches!(err, LevelError::EnemySpawnOverlap { line: 6, pos } if pos == ChunkPos::new(5, 1, 2)));
+}
+
+#[test]
+fn reports_unpaired_portals() {
+    assert!(matches!(parse("portal 2 1 2 3 +x"), Err(LevelError::PortalLink { link: 3, count: 1 })));
+    let three = "portal 2 1 2 3 +x\nportal 4 1 4 3 +x\nportal 6 1 6 3 +x";
+    assert!(matches!(parse(three), Err(LevelError::PortalLink { link: 3, count: 3 })));
+    assert!(parse("portal 2 1 2 3 +x\nportal 4 1 4 3 -z").is_ok());
+}
+
+#[test]
+fn rejects_worlds_over_the_size_cap() {
+    let registry = BlockRegistry::default();
+    for size in ["0 1 1", "1 -1 1", "4097 1 1", "64 64 2"] {
+        let source = format!("name test\nsize {}\nplayer 1 1 1", size);
+        assert!(matches!(Level::parse(&source, &registry), Err(LevelError::InvalidSize(_))), "size {}", size);
+    }
+}
@@ -0,0 +1,41 @@
+//! recorded runs as regression tests. A simulation change that is meant to play differently breaks these,
+//! record them again with `anabolic --record tests/replays/floor.anrp --seed 1` once the new behavior is wanted.
+//!
+//! The fingerprint in floor.anrp only holds on the platform it was recorded on, x86_64 Linux. Steering and
+//! aiming go through f32 `sin`/`cos`, which std leaves to the platform libm, and other libms round the last
+//! bit differently. Elsewhere the check is skipped rather than failed
+
+use anabolic::replay::{ Replay, ReplayError, RunStart };
+
+#[test]
+#[cfg_attr(
+    not(all(target_os = "linux", target_arch = "x86_64")),
+    ignore = "floor.anrp was recorded with the x86_64 Linux libm"
+)]
+fn recorded_floor_run_plays_out_the_same() {
+    let replay = Replay::load_from_file("tests/replays/floor.anrp").expect("recorded replay should load");
+    assert_eq!(replay.start, RunStart::Floor);
+    assert!(!replay.inputs.is_empty());
+    assert!(matches!(replay.verify(), Ok(true)));
+}
+
+#[test]
+fn replay_bytes_round_trip() {
+    let replay = Replay::load_from_file("tests/replays/floor.anrp").expect("recorded replay should load");
+    let decoded = Replay::from_bytes(&replay.to_bytes()).expect("written replay should decode");
+    assert_eq!(decoded.start, replay.start);
+    assert_eq!(decoded.seed, replay.seed);
+    assert_eq!(decoded.inputs, replay.inputs);
+    assert_eq!(decoded.fingerprint, replay.fingerprint);
+}
+
+#[test]
+fn arena_replay_refuses_an_edited_level() {
+    let RunStart::Level { path, hash } = RunStart::level("levels/default.lvl").expect("default level should load") else {
+        panic!("levels start arena runs");
+    };
+    let current = Replay::new(RunStart::Level { path: path.clone(), hash }, 1);
+    assert!(current.start_run().is_ok());
+    let edited = Replay::new(RunStart::Level { path, hash: hash ^ 1 }, 1);
+    assert!(matches!(edited.start_run(), Err(ReplayError::LevelChanged(_))));
+}
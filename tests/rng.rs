@@ -0,0 +1,61 @@
+//! the run RNG, one seed and a separate stream per system
+
+use anabolic::replay::Replay;
+use shared::types::{ Rng, RunRng };
+
+fn draw(rng: &mut Rng) -> Vec<u64> {
+    (0..16).map(|_| rng.next_u64()).collect()
+}
+
+#[test]
+fn same_seed_gives_the_same_streams() {
+    let mut first = RunRng::new(42);
+    let mut second = RunRng::new(42);
+    assert_eq!(first, second);
+    assert_eq!(draw(&mut first.spawning), draw(&mut second.spawning));
+    assert_eq!(draw(&mut first.loot), draw(&mut second.loot));
+    assert_eq!(draw(&mut first.ai), draw(&mut second.ai));
+
+    let mut other = RunRng::new(43);
+    assert_ne!(draw(&mut RunRng::new(42).spawning), draw(&mut other.spawning));
+}
+
+#[test]
+fn streams_differ_even_for_small_seeds() {
+    for seed in 0..8 {
+        let rng = RunRng::new(seed);
+        assert_ne!(rng.spawning, rng.loot, "seed {}", seed);
+        assert_ne!(rng.spawning, rng.ai, "seed {}", seed);
+        assert_ne!(rng.loot, rng.ai, "seed {}", seed);
+    }
+}
+
+#[test]
+fn drawing_from_one_stream_leaves_the_others_alone() {
+    let untouched = RunRng::new(7);
+    let mut rng = RunRng::new(7);
+    for _ in 0..100 {
+        rng.loot.next_u64();
+        rng.loot.gen_range(0, 10);
+    }
+    assert_ne!(rng.loot, untouched.loot);
+    assert_eq!(rng.spawning, untouched.spawning);
+    assert_eq!(rng.ai, untouched.ai);
+
+    let mut spawning = untouched.spawning;
+    assert_eq!(draw(&mut rng.spawning), draw(&mut spawning));
+}
+
+#[test]
+fn spawning_enemies_only_draws_from_the_spawning_stream() {
+    let replay = Replay::load_from_file("tests/replays/floor.anrp").expect("recorded replay should load");
+    let (mut world, mut spawner) = replay.start_run().expect("recorded run should start");
+    let fresh = world.rng;
+    for input in &replay.inputs[..600] {
+        world.update(&mut spawner, input);
+    }
+    assert!(!world.enemies.bodies.is_empty(), "the recording reaches a combat room by now");
+    assert_ne!(world.rng.spawning, fresh.spawning);
+    assert_eq!(world.rng.loot, fresh.loot);
+    assert_eq!(world.rng.ai, fresh.ai);
+}
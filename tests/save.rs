@@ -0,0 +1,64 @@
+//! saves written partway through a run, and what loading does with damaged files
+
+use anabolic::{
+    replay::{ fingerprint, Replay, RunStart },
+    save::{ load_run, save_run, SaveError },
+    spawning::SpawningSystem,
+    World,
+};
+
+fn floor_run_partway(ticks: usize) -> (World, SpawningSystem, Replay) {
+    let replay = Replay::load_from_file("tests/replays/floor.anrp").expect("recorded replay should load");
+    let (mut world, mut spawner) = replay.start_run().expect("recorded run should start");
+    for input in &replay.inputs[..ticks] {
+        world.update(&mut spawner, input);
+    }
+    (world, spawner, replay)
+}
+
+#[test]
+fn loaded_save_plays_on_the_same() {
+    let (mut world, mut spawner, replay) = floor_run_partway(400);
+    let (mut loaded, mut loaded_spawner) = load_run(&save_run(&world, &spawner)).expect("fresh save should load");
+    assert_eq!(fingerprint(&loaded, &loaded_spawner), fingerprint(&world, &spawner));
+
+    for input in &replay.inputs[400..600] {
+        world.update(&mut spawner, input);
+        loaded.update(&mut loaded_spawner, input);
+    }
+    assert_eq!(fingerprint(&loaded, &loaded_spawner), fingerprint(&world, &spawner));
+}
+
+#[test]
+fn truncated_save_is_rejected() {
+    let (world, spawner) = Replay::new(RunStart::Floor, 3).start_run().expect("floor run should start");
+    let bytes = save_run(&world, &spawner);
+    for len in (0..bytes.len()).step_by(bytes.len() / 200) {
+        assert!(load_run(&bytes[..len]).is_err(), "{} of {} bytes loaded", len, bytes.len());
+    }
+    assert!(matches!(load_run(&bytes[..2]), Err(SaveError::NotASave)));
+}
+
+#[test]
+fn corrupted_save_does_not_panic() {
+    let (world, spawner) = Replay::new(RunStart::Floor, 3).start_run().expect("floor run should start");
+    let bytes = save_run(&world, &spawner);
+
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] ^= 0xFF;
+    assert!(matches!(load_run(&wrong_magic), Err(SaveError::NotASave)));
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] ^= 0xFF;
+    assert!(matches!(load_run(&wrong_version), Err(SaveError::UnsupportedVersion(_))));
+
+    // flipped bits may still decode to a valid run, loading only has to come back without panicking.
+    // Every byte near the start, then a spread over the rest of the file
+    let spread = (8..bytes.len()).step_by(bytes.len() / 100);
+    for index in (8..128).chain(spread) {
+        for flip in [0x01, 0x80, 0xFF] {
+            let mut corrupted = bytes.clone();
+            corrupted[index] ^= flip;
+            let _ = load_run(&corrupted);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! palette files that map MagicaVoxel color indices to blocks
+
+use anabolic::{
+    level::LevelError,
+    vox_level::{ PaletteEntry, VoxPalette },
+};
+use shared::types::{ BlockRegistry, BlockTypeId, EnemyType, PossibleEnemySizes };
+
+fn parse(source: &str) -> Result<VoxPalette, LevelError> {
+    VoxPalette::parse(source, &BlockRegistry::default())
+}
+
+#[test]
+fn default_palette_parses() {
+    let palette = VoxPalette::load("levels/default.palette", &BlockRegistry::default())
+        .expect("default palette should load");
+    assert_eq!(palette.entries[1], PaletteEntry::Block(BlockTypeId::STONE));
+    assert_eq!(palette.entries[249], PaletteEntry::PlayerStart);
+    assert_eq!(palette.entries[253], PaletteEntry::Enemy(EnemyType::Regular, PossibleEnemySizes::BOSS));
+}
+
+#[test]
+fn default_line_fills_every_unlisted_index() {
+    let palette = parse("default empty\n7 block crate").expect("palette should parse");
+    assert_eq!(palette.entries[7], PaletteEntry::Block(BlockTypeId::CRATE));
+    assert!(palette.entries.iter().enumerate().all(|(i, entry)| i == 7 || *entry == PaletteEntry::Empty));
+
+    // listed indices win no matter where the default line stands
+    let palette = parse("7 block crate\ndefault empty").expect("palette should parse");
+    assert_eq!(palette.entries[7], PaletteEntry::Block(BlockTypeId::CRATE));
+    assert_eq!(palette.entries[8], PaletteEntry::Empty);
+
+    let palette = parse("# nothing listed").expect("palette should parse");
+    assert!(palette.entries.iter().all(|entry| *entry == PaletteEntry::Block(BlockTypeId::STONE)));
+}
+
+#[test]
+fn rejects_indices_outside_the_editor_palette() {
+    for source in ["0 solid", "256 solid", "-1 solid", "red solid"] {
+        assert!(matches!(parse(source), Err(LevelError::Syntax { line: 1, .. })), "{}", source);
+    }
+    assert!(parse("255 solid").is_ok());
+}
+
+#[test]
+fn rejects_unknown_entries() {
+    for source in ["1 lava", "1 block cheese", "1 enemy regular tiny", "1 portal 0 up", "1 solid stone", "default"] {
+        assert!(matches!(parse(source), Err(LevelError::Syntax { line: 1, .. })), "{}", source);
+    }
+    assert!(matches!(parse("1 solid\n\n3 wobbly"), Err(LevelError::Syntax { line: 3, .. })));
+}